@@ -1,11 +1,12 @@
 use complexity::Complexity;
 use maxHeap::BinaryHeap;
 use num_traits::Zero;
+use search_problem::{never_redundant, no_pruning, Closures, SearchProblem};
 use std::cmp::{Ord, Ordering, PartialOrd};
-use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
+use zobrist::BuildZobristHasher;
 
 #[derive(Clone)]
 struct State<N, C>
@@ -23,21 +24,15 @@ where
 
     /// Dir of predecessor
     pub predecessor: Option<Box<State<N, C>>>,
-
-    /// Key of the State
-    hash: u64,
 }
 
 impl<N: Clone + Hash, C: Zero + Ord + Copy + Debug> State<N, C> {
     pub fn new(predecessor: Option<Box<State<N, C>>>, gcost: C, taquin: N) -> State<N, C> {
-        let mut hash = DefaultHasher::new();
-        taquin.hash(&mut hash);
         State {
             gcost,
             hcost: C::zero(),
             taquin,
             predecessor,
-            hash: hash.finish(), // rewrite this
         }
     }
 }
@@ -93,21 +88,44 @@ where
     FS: Fn(&N) -> bool,
     FA: Fn(&N, A) -> N,
     A: Copy,
+{
+    search(
+        &Closures::new(
+            neighbours_actions,
+            perform_action,
+            heuristic,
+            success,
+            (),
+            no_pruning,
+            never_redundant,
+        ),
+        start,
+    )
+}
+
+pub fn search<P>(problem: &P, start: &P::State) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq + Debug,
 {
     const DEFAULT_OPEN_SET_SIZE: usize = 0x1_0000;
     const DEFAULT_CLOSED_SET_SIZE: usize = 0x1_0000;
 
-    let mut open_set: BinaryHeap<State<N, C>> = BinaryHeap::with_capacity(DEFAULT_OPEN_SET_SIZE);
-    let mut closed_set = HashSet::with_capacity(DEFAULT_CLOSED_SET_SIZE);
-    open_set.push(State::new(None, C::zero(), start.clone()));
+    let mut open_set: BinaryHeap<State<P::State, P::Cost>> = BinaryHeap::with_capacity(DEFAULT_OPEN_SET_SIZE);
+    let mut closed_set =
+        HashSet::with_capacity_and_hasher(DEFAULT_CLOSED_SET_SIZE, BuildZobristHasher::default());
+    open_set.push(State::new(None, P::Cost::zero(), start.clone()));
     let mut complexity = Complexity {
         in_time: 0,
         in_size: 0,
     };
 
     while !open_set.is_empty() {
+        if problem.interrupted() {
+            return None;
+        }
         complexity.in_time += 1;
-        if success(
+        if problem.is_goal(
             &open_set
                 .peek()
                 .expect("Tried to peek none existing open state")
@@ -120,15 +138,19 @@ where
         let current_state = open_set
             .pop()
             .expect("Tried to pop none existing open state");
+        problem.progress(
+            Some(current_state.gcost + current_state.hcost),
+            open_set.len(),
+        );
         //k        println!("while current_state: {:?}", current_state.taquin);
 
-        for (action, cost) in neighbours_actions(&current_state.taquin) {
+        for (action, cost) in problem.successors(&current_state.taquin) {
             let mut state = State::new(
                 Some(Box::new(current_state.clone())),
                 current_state.gcost + cost,
-                perform_action(&current_state.taquin, action),
+                problem.apply(&current_state.taquin, action),
             );
-            state.hcost = heuristic(&state.taquin);
+            state.hcost = problem.heuristic(&state.taquin);
 
             //println!("neighbour: {}", state);
             if !closed_set.get(&state).is_some()
@@ -0,0 +1,217 @@
+use complexity::Complexity;
+use num_traits::Zero;
+use rand::{thread_rng, Rng};
+use search_problem::SearchProblem;
+use std::collections::HashSet;
+use std::hash::Hash;
+use zobrist::BuildZobristHasher;
+
+/// Width of the beam when the caller sets none
+pub const DEFAULT_WIDTH: usize = 1000;
+
+/// Settings of a beam search
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Beam {
+    /// States kept in each layer
+    pub width: usize,
+    /// Break the ties at random instead of keeping the first generated
+    pub stochastic: bool,
+    /// Keep a state only once in a layer, and never again once it was kept
+    /// in a previous one: without it a narrow beam can go round in circles,
+    /// with it every kept state stays in memory
+    pub dedup: bool,
+}
+
+impl Beam {
+    /// Deterministic beam of `width` states, without duplicates
+    pub fn new(width: usize) -> Self {
+        Beam {
+            width: width.max(1),
+            stochastic: false,
+            dedup: true,
+        }
+    }
+
+    pub fn stochastic(mut self, stochastic: bool) -> Self {
+        self.stochastic = stochastic;
+        self
+    }
+
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+}
+
+impl Default for Beam {
+    fn default() -> Self {
+        Beam::new(DEFAULT_WIDTH)
+    }
+}
+
+/// Successor of a state of the layer, candidate to the next one
+struct Candidate<S, A, C, R> {
+    f_cost: C,
+    g_cost: C,
+    state: S,
+    action: A,
+    pruning: R,
+    /// Index of its predecessor in the history
+    parent: usize,
+}
+
+/// Deterministic beam search of `width` states without duplicates
+pub fn search<P>(problem: &P, start: &P::State, width: usize) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+{
+    search_with(problem, start, Beam::new(width))
+}
+
+/// Breadth first search keeping only the `beam.width` states of lowest
+/// f-cost in each layer: memory and time per layer are bounded, which finds
+/// short but not optimal solutions on boards too big for astar. Gives up if
+/// the beam dies out.
+pub fn search_with<P>(
+    problem: &P,
+    start: &P::State,
+    beam: Beam,
+) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+{
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 1,
+    };
+    // predecessor and action of every state ever kept, the path is replayed at
+    // the end since without dedup the states are only kept for the current
+    // layer
+    let mut history: Vec<Option<(usize, P::Action)>> = vec![None];
+    let mut layer = vec![(start.clone(), P::Cost::zero(), problem.initial_pruning(), 0)];
+    if problem.is_goal(start) {
+        return Some((vec![start.clone()], complexity));
+    }
+    let replay = |history: &[Option<(usize, P::Action)>], mut index: usize| {
+        let mut actions = Vec::new();
+        while let Some((parent, action)) = history[index] {
+            actions.push(action);
+            index = parent;
+        }
+        let mut path = vec![start.clone()];
+        for &action in actions.iter().rev() {
+            let next = problem.apply(path.last().unwrap(), action);
+            path.push(next);
+        }
+        path.reverse();
+        path
+    };
+    let mut rng = thread_rng();
+    // states kept in the previous layers
+    let mut kept = HashSet::with_hasher(BuildZobristHasher::default());
+    kept.insert(start.clone());
+    let mut seen = HashSet::with_hasher(BuildZobristHasher::default());
+    while !layer.is_empty() {
+        let mut candidates = Vec::new();
+        seen.clear();
+        problem.progress(None, layer.len());
+        for (state, g_cost, pruning, index) in layer.drain(..) {
+            if problem.interrupted() {
+                return None;
+            }
+            complexity.in_time += 1;
+            for (a, c) in problem.successors(&state) {
+                let next_pruning = problem.next_pruning(&pruning, a);
+                if problem.is_pruned(&next_pruning) {
+                    continue;
+                }
+                let next = problem.apply(&state, a);
+                if beam.dedup && (kept.contains(&next) || !seen.insert(next.clone())) {
+                    continue;
+                }
+                if problem.is_goal(&next) {
+                    history.push(Some((index, a)));
+                    return Some((replay(&history, history.len() - 1), complexity));
+                }
+                candidates.push(Candidate {
+                    f_cost: g_cost + c + problem.heuristic(&next),
+                    g_cost: g_cost + c,
+                    state: next,
+                    action: a,
+                    pruning: next_pruning,
+                    parent: index,
+                });
+            }
+        }
+        if beam.stochastic {
+            rng.shuffle(&mut candidates);
+        }
+        // stable, the first generated come first among equals
+        candidates.sort_by_key(|candidate| candidate.f_cost);
+        candidates.truncate(beam.width);
+        complexity.in_size = complexity.in_size.max(candidates.len());
+        for candidate in candidates {
+            if beam.dedup {
+                kept.insert(candidate.state.clone());
+            }
+            history.push(Some((candidate.parent, candidate.action)));
+            layer.push((
+                candidate.state,
+                candidate.g_cost,
+                candidate.pruning,
+                history.len() - 1,
+            ));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar;
+    use heuristic::Manhattan;
+    use search_problem::TaquinProblem;
+    use taquin::Taquin;
+
+    #[test]
+    fn beam() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+        let (optimal, _) = astar::search(&problem, &taquin).unwrap();
+        for &beam in [
+            Beam::new(5),
+            Beam::new(10).stochastic(true),
+            Beam::new(100).dedup(false),
+        ]
+        .iter()
+        {
+            let (path, complexity) = search_with(&problem, &taquin, beam).unwrap();
+            assert_eq!(path.first(), Some(&spiral));
+            assert_eq!(path.last(), Some(&taquin));
+            assert!(path.windows(2).all(|w| w[0].dir_to(&w[1]).is_some()));
+            assert!(path.len() >= optimal.len());
+            assert!(complexity.in_size <= beam.width);
+        }
+        // a single state cannot go back: it ends in a dead end
+        assert!(search(&problem, &taquin, 1).is_none());
+        // wide enough to hold a whole layer, it is a breadth first search
+        let (path, _) = search(&problem, &taquin, 100_000).unwrap();
+        assert_eq!(path.len(), optimal.len());
+    }
+    #[test]
+    fn big() {
+        let spiral = Taquin::spiral(5);
+        let taquin = (0..200).fold(spiral.clone(), |t, i| {
+            let dirs = t.neighbours();
+            t.move_piece(dirs[i * 7 % dirs.len()]).unwrap()
+        });
+        let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+        let (path, _) = search(&problem, &taquin, 100).unwrap();
+        assert_eq!(path.first(), Some(&spiral));
+        assert_eq!(path.last(), Some(&taquin));
+    }
+}
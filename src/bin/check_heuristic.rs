@@ -0,0 +1,95 @@
+extern crate getopts;
+extern crate npuzzle;
+use getopts::Options;
+use npuzzle::heuristic;
+use npuzzle::heuristic_check::{check, MAX_CHECK_DIM};
+use npuzzle::taquin::Taquin;
+use std::env;
+use std::str::FromStr;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optopt("q", "heuristic", "heuristic to check, all by default", "NAME");
+    opts.optopt("s", "", "size of the taquin", "INTEGER");
+    opts.optopt("n", "", "number of counterexamples to print", "INTEGER");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            eprintln!("{}", f);
+            ::std::process::exit(1);
+        }
+    };
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+    let size = match usize::from_str(&matches.opt_str("s").unwrap_or("3".to_string())) {
+        Ok(size) => size,
+        Err(e) => {
+            eprintln!("{}", e);
+            print_usage(&program, opts);
+            ::std::process::exit(1);
+        }
+    };
+    if size == 0 || size > MAX_CHECK_DIM {
+        eprintln!("Invalid size specified: {}", size);
+        ::std::process::exit(1);
+    }
+    let nb_counterexamples =
+        match usize::from_str(&matches.opt_str("n").unwrap_or("3".to_string())) {
+            Ok(n) => n,
+            Err(e) => {
+                eprintln!("{}", e);
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        };
+    let heuristics = match matches.opt_str("q") {
+        Some(name) => match heuristic::by_name(&name) {
+            Some(h) => vec![h],
+            None => {
+                eprintln!("Unknown heuristic");
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        },
+        None => heuristic::builtins(),
+    };
+
+    let spiral = Taquin::spiral(size);
+    let mut all_ok = true;
+    for h in heuristics {
+        let report = check(&*h, &spiral, nb_counterexamples).unwrap();
+        println!(
+            "{}: {} states, {} overestimates, {} inconsistent edges",
+            h.name(),
+            report.nb_states,
+            report.nb_overestimates,
+            report.nb_inconsistencies
+        );
+        for violation in &report.counterexamples {
+            println!("{}", violation);
+        }
+        if report.is_admissible() != h.is_admissible() || report.is_consistent() != h.is_consistent() {
+            all_ok = false;
+            println!(
+                "{} claims admissible: {}, consistent: {}",
+                h.name(),
+                h.is_admissible(),
+                h.is_consistent()
+            );
+        }
+    }
+    if !all_ok {
+        ::std::process::exit(1);
+    }
+}
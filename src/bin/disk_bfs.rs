@@ -0,0 +1,96 @@
+extern crate getopts;
+extern crate npuzzle;
+use getopts::Options;
+use npuzzle::disk_bfs::DiskBfs;
+use npuzzle::taquin::Taquin;
+use std::env;
+use std::str::FromStr;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options] DIR", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optopt("s", "", "size of the taquin, 4 by default", "INTEGER");
+    opts.optopt(
+        "p",
+        "pattern",
+        "tiles told apart from the others, all by default",
+        "TILE,TILE,...",
+    );
+    opts.optopt(
+        "d",
+        "",
+        "stop after this depth, the search resumes from DIR",
+        "INTEGER",
+    );
+    opts.optopt("", "run-len", "boards sorted in memory at once", "NB");
+    opts.optflag("", "keep-layers", "keep the file of every layer in DIR");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            eprintln!("{}", f);
+            ::std::process::exit(1);
+        }
+    };
+    if matches.opt_present("h") || matches.free.len() != 1 {
+        print_usage(&program, opts);
+        return;
+    }
+    let parse = |name: &str| {
+        matches.opt_str(name).map(|s| match usize::from_str(&s) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                ::std::process::exit(1);
+            }
+        })
+    };
+    let size = parse("s").unwrap_or(4);
+    if size < 2 {
+        eprintln!("Invalid size specified: {}", size);
+        ::std::process::exit(1);
+    }
+    let pattern = match matches.opt_str("p") {
+        Some(tiles) => match tiles.split(',').map(|t| u64::from_str(t.trim())).collect() {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                eprintln!("pattern: {}", e);
+                ::std::process::exit(1);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let dir = &matches.free[0];
+    let mut bfs = match DiskBfs::new(dir, &Taquin::spiral(size), &pattern) {
+        Ok(bfs) => bfs.keep_layers(matches.opt_present("keep-layers")),
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    };
+    if let Some(run_len) = parse("run-len") {
+        bfs = bfs.run_len(run_len);
+    }
+    if let Some(depth) = parse("d") {
+        bfs = bfs.max_depth(depth);
+    }
+    match bfs.run_with(|depth, count| {
+        if count != 0 {
+            println!("{:>3}: {}", depth, count);
+        }
+    }) {
+        Ok(counts) => println!("total: {}", counts.iter().sum::<u64>()),
+        Err(e) => {
+            eprintln!("{}: {}", dir, e);
+            ::std::process::exit(1);
+        }
+    }
+}
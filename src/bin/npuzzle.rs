@@ -3,23 +3,22 @@ extern crate itertools;
 extern crate npuzzle;
 extern crate sdl2;
 
-use getopts::Options;
-use npuzzle::astar::astar;
-use npuzzle::greedy_search::greedy_search;
-use npuzzle::idastar::idastar;
+use getopts::{Matches, Options};
+use npuzzle::beam_search::{self, Beam};
+use npuzzle::distance_table::{DistanceTable, MAX_TABLE_DIM};
+use npuzzle::greedy_search::TieBreak;
+use npuzzle::heuristic::{self, Manhattan};
+use npuzzle::pruning;
+use npuzzle::trie::Automaton;
+use npuzzle::solver::{Algorithm, Progress, SolveError, Solver};
 use npuzzle::taquin::Taquin;
-use npuzzle::trie::*;
 use npuzzle::visualizable::*;
 use std::env;
-use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::iter::repeat;
+use std::path::Path;
 use std::str::FromStr;
-extern crate bincode;
-use bincode::deserialize;
-use std::time::SystemTime;
-use npuzzle::complexity::Complexity;
+use std::time::{Duration, SystemTime};
 
 fn read_file(filename: &str) -> Result<String, std::io::Error> {
     let mut f = File::open(filename)?;
@@ -28,6 +27,59 @@ fn read_file(filename: &str) -> Result<String, std::io::Error> {
     Ok(s)
 }
 
+/// Parse the value of an option, exiting on invalid input
+fn parse_opt<T: FromStr>(matches: &Matches, name: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    matches.opt_str(name).map(|s| match s.parse() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            ::std::process::exit(1);
+        }
+    })
+}
+
+/// Status line of --progress, rewritten in place on stderr
+fn print_progress(progress: &Progress<u64>) {
+    let bound = progress
+        .bound
+        .map_or(String::from("-"), |bound| bound.to_string());
+    let secs = progress.elapsed.as_secs_f64();
+    eprint!(
+        "\r{:.1}s  expanded: {} ({:.0}/s)  bound: {}  open: {}\x1b[K",
+        secs,
+        progress.expanded,
+        progress.expanded as f64 / secs.max(0.001),
+        bound,
+        progress.open
+    );
+}
+
+/// Erase the status line of --progress before printing the result
+fn end_progress<T>(matches: &Matches, result: T) -> T {
+    if matches.opt_present("progress") {
+        eprint!("\r\x1b[K");
+    }
+    result
+}
+
+fn print_elapsed(since: SystemTime) {
+    match since.elapsed() {
+        Ok(elapsed) => {
+            println!(
+                "RESOLVED TIME:\t\t{} secondes and {} milisecondes",
+                elapsed.as_secs(),
+                elapsed.subsec_millis()
+            );
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+        }
+    }
+}
+
 fn print_usage(program: &str, opts: Options) {
     let brief = format!("Usage: {} FILENAME [options]", program);
     print!("{}", opts.usage(&brief));
@@ -38,7 +90,12 @@ fn main() {
     let program = args[0].clone();
 
     let mut opts = Options::new();
-    opts.optopt("a", "automaton", "serde file of automaton", "PATH");
+    opts.optopt(
+        "a",
+        "automaton",
+        "serde or flat file of automaton, idastar builds and caches one otherwise",
+        "PATH",
+    );
     opts.optopt(
         "v",
         "visu",
@@ -55,14 +112,64 @@ fn main() {
         "g",
         "alg",
         "Algorithm",
-        "(astar | idastar | uniform_cost | greedy_search)",
+        "(astar | frontier_astar | idastar | rbfs | sma_star | uniform_cost | breadth_first\n        | greedy_search | depth_first | reduction | beam)",
     );
     opts.optopt(
         "q",
         "heurisique",
         "Heuristique",
-        "(manhattan | linear_conflict | hamming_distance)",
+        "(manhattan | linear_conflict | hamming_distance | zero | max(a,b,...))",
+    );
+    opts.optflag("", "list-heuristics", "list the available heuristics");
+    opts.optopt(
+        "t",
+        "table",
+        "distance table of the 3x3 boards, built if the file does not exist",
+        "PATH",
+    );
+    opts.optopt(
+        "",
+        "tie-break",
+        "order of the boards of equal heuristic in greedy search",
+        "(newest | oldest | lowest_cost | highest_cost)",
+    );
+    opts.optopt("", "max-nodes", "give up after expanding NB nodes", "NB");
+    opts.optopt("", "time-limit", "give up after SECS secondes", "SECS");
+    opts.optopt("", "memory", "nodes kept in memory by sma_star", "NB");
+    opts.optopt(
+        "",
+        "beam-width",
+        &format!("boards kept in each layer by beam (default {})", beam_search::DEFAULT_WIDTH),
+        "NB",
+    );
+    opts.optflag("", "stochastic", "break the ties of beam at random");
+    opts.optflag(
+        "",
+        "no-dedup",
+        "let beam keep a board several times, faster but it can loop",
     );
+    opts.optopt("j", "threads", "number of threads used by idastar", "NB");
+    opts.optflagopt(
+        "",
+        "all-optimal",
+        "print every optimal solution with idastar, at most MAX",
+        "MAX",
+    );
+    opts.optflag("", "count-only", "with --all-optimal, only count the solutions");
+    opts.optflag("", "progress", "show the progress of the search on stderr");
+    opts.optopt(
+        "",
+        "checkpoint",
+        "save the progress of idastar in FILE to resume it later",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "checkpoint-interval",
+        "secondes between two checkpoints, 60 by default",
+        "SECS",
+    );
+    opts.optflag("", "resume", "resume idastar from its --checkpoint");
 	opts.optopt(
         "u",
         "user",
@@ -82,24 +189,41 @@ fn main() {
         print_usage(&program, opts);
         return;
     }
+    if matches.opt_present("list-heuristics") {
+        for h in heuristic::builtins() {
+            println!(
+                "{:20}admissible: {:6}consistent: {}",
+                h.name(),
+                h.is_admissible(),
+                h.is_consistent()
+            );
+        }
+        println!("{:20}maximum of several admissible heuristics", "max(a,b,...)");
+        return;
+    }
 
     let algorithm = match matches.opt_str("g") {
-        Some(a) => a,
-        None => String::from_str("astar").unwrap(),
+        Some(a) => match a.parse::<Algorithm>() {
+            Ok(a) => a,
+            Err(e) => {
+                eprintln!("{}", e);
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        },
+        None => Algorithm::AStar,
     };
 
-    let heuristique: fn(&Taquin, &Taquin) -> u64 = match matches.opt_str("q") {
-        Some(s) => match s.as_str() {
-            "manhattan" => |t: &Taquin, s: &Taquin| t.manhattan_heuristic(s),
-            "linear_conflict" => |t: &Taquin, s: &Taquin| t.manhattan_heuristic_linear_conflict(s),
-            "hamming_distance" => |t: &Taquin, s: &Taquin| t.hamming_distance_heuristic(s),
-            _ => {
-                eprintln!("Unknown algorithm");
+    let heuristique = match matches.opt_str("q") {
+        Some(s) => match heuristic::by_name(&s) {
+            Some(h) => h,
+            None => {
+                eprintln!("Unknown heuristic");
                 print_usage(&program, opts);
                 ::std::process::exit(1);
             }
         },
-        None => |t: &Taquin, s: &Taquin| t.manhattan_heuristic(s),
+        None => Box::new(Manhattan),
     };
 
     let taquin = match matches.opt_str("r") {
@@ -144,95 +268,172 @@ fn main() {
     println!("TAQUIN: {}", taquin);
     let spiral = Taquin::spiral(taquin.dim());
 
-    if !taquin.is_solvable(&spiral) {
-        println!("this is unsolvable");
-        return;
+    let automaton: Box<dyn Automaton>;
+    let observer = print_progress;
+    let mut solver = Solver::new()
+        .algorithm(algorithm)
+        .heuristic(&*heuristique)
+        .default_automaton(true);
+    if let Some(automaton_file) = matches.opt_str("a") {
+        if !algorithm.uses_automaton() {
+            eprintln!("warning: the automaton is ignored by {:?}", algorithm);
+        }
+        automaton = match pruning::load_automaton(automaton_file, taquin.dim()) {
+            Ok(automaton) => automaton,
+            Err(e) => {
+                eprintln!("{}", e);
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        };
+        solver = solver.automaton(&*automaton);
+    }
+    let table: DistanceTable;
+    if let Some(table_file) = matches.opt_str("t") {
+        if taquin.dim() <= MAX_TABLE_DIM {
+            table = if Path::new(&table_file).exists() {
+                match DistanceTable::load(&table_file) {
+                    Ok(table) => table,
+                    Err(e) => {
+                        eprintln!("{}: {}", table_file, e);
+                        ::std::process::exit(1);
+                    }
+                }
+            } else {
+                let table = DistanceTable::build(&spiral).unwrap();
+                if let Err(e) = table.save(&table_file) {
+                    eprintln!("{}: {}", table_file, e);
+                }
+                table
+            };
+            solver = solver.table(&table);
+        }
+    }
+    if let Some(name) = matches.opt_str("tie-break") {
+        match TieBreak::by_name(&name) {
+            Some(tie_break) => solver = solver.tie_break(tie_break),
+            None => {
+                eprintln!("Unknown tie break");
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        }
+    }
+    if let Some(max_nodes) = parse_opt(&matches, "max-nodes") {
+        solver = solver.max_nodes(max_nodes);
+    }
+    if let Some(time_limit) = parse_opt(&matches, "time-limit") {
+        solver = solver.time_limit(Duration::from_secs(time_limit));
+    }
+    if let Some(max_nodes) = parse_opt(&matches, "memory") {
+        solver = solver.memory(max_nodes);
+    }
+    let beam_width = parse_opt(&matches, "beam-width").unwrap_or(beam_search::DEFAULT_WIDTH);
+    solver = solver.beam(
+        Beam::new(beam_width)
+            .stochastic(matches.opt_present("stochastic"))
+            .dedup(!matches.opt_present("no-dedup")),
+    );
+    if let Some(threads) = parse_opt(&matches, "j") {
+        solver = solver.threads(threads);
+    }
+    if matches.opt_present("progress") {
+        solver = solver.progress(&observer, Duration::from_millis(250));
+    }
+    if let Some(file) = matches.opt_str("checkpoint") {
+        if algorithm != Algorithm::IdaStar {
+            eprintln!("--checkpoint only works with idastar");
+            ::std::process::exit(1);
+        }
+        if matches.opt_present("j") {
+            eprintln!("--checkpoint runs idastar on a single thread, -j can't be used");
+            ::std::process::exit(1);
+        }
+        let interval = parse_opt(&matches, "checkpoint-interval").unwrap_or(60);
+        solver = solver
+            .checkpoint(file, Duration::from_secs(interval))
+            .resume(matches.opt_present("resume"));
+    } else if matches.opt_present("resume") {
+        eprintln!("--resume needs a --checkpoint file");
+        ::std::process::exit(1);
     }
     let now = SystemTime::now();
 
-	let mut sol;
-	if !taquin.is_solved(&spiral) {
-		sol = match algorithm.as_str() {
-			"idastar" => {
-				let automaton_file = match matches.opt_str("a") {
-					Some(file) => file,
-					None => {
-						eprintln!("You should specify a prunning file for idastar");
-						print_usage(&program, opts);
-						::std::process::exit(1);
-					}
-				};
-				let automaton: Trie = match deserialize(&fs::read(automaton_file).unwrap()[..]) {
-					Ok(file) => file,
-					Err(e) => {
-						eprintln!("{}", e);
-						print_usage(&program, opts);
-						::std::process::exit(1);
-					}
-				};
-				idastar(
-					&taquin,
-					|t| t.neighbours().into_iter().zip(repeat(1)),
-					|t, a| t.move_piece(a).unwrap(),
-					|t| heuristique(t, &spiral),
-					|t| t.is_solved(&spiral),
-					TrieType::Match(0),
-					|old_state, dir| automaton.change_true_state(old_state, dir),
-					|t| *t == TrieType::Redundant,
-				).unwrap()
-			}
-			"greedy_search" => {
-				greedy_search(
-					&taquin,
-					|t| t.sorted_neighbours(&|t| heuristique(t, &spiral)).into_iter().zip(repeat(1)),
-					|t, a| t.move_piece(a).unwrap(),
-					|t| heuristique(t, &spiral),
-					|t| t.is_solved(&spiral),
-				).unwrap()
-			}
-			"astar" => astar(
-				&taquin,
-				|t| t.neighbours().into_iter().zip(repeat(1)),
-				|t, a| t.move_piece(a).unwrap(),
-				|t| heuristique(t, &spiral),
-				|t| t.is_solved(&spiral),
-			).unwrap(),
-			"uniform_cost" => astar(
-				&taquin,
-				|t| t.neighbours().into_iter().zip(repeat(1)),
-				|t, a| t.move_piece(a).unwrap(),
-				|_t| 0,
-				|t| t.is_solved(&spiral),
-			).unwrap(),
-			_ => {
-				eprintln!("Unknown algorithm");
-				print_usage(&program, opts);
-				::std::process::exit(1);
-			}
-		};
-	} else {
-		sol = (vec![taquin], Complexity { in_time: 0, in_size: 1 });
-	}
-    println!("PATH: ");
-    sol.0.reverse();
-    for p in &sol.0 {
-        println!("{}", p);
+    if matches.opt_present("all-optimal") {
+        let max_solutions = parse_opt(&matches, "all-optimal");
+        if max_solutions == Some(0) {
+            eprintln!("--all-optimal needs at least one solution");
+            ::std::process::exit(1);
+        }
+        let count_only = matches.opt_present("count-only");
+        let all = match end_progress(
+            &matches,
+            solver.all_optimal(&taquin, max_solutions, count_only),
+        ) {
+            Ok(all) => all,
+            Err(SolveError::Unsolvable) => {
+                println!("this is unsolvable");
+                return;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                ::std::process::exit(1);
+            }
+        };
+        for moves in all.solutions.iter() {
+            let moves: Vec<String> = moves.iter().map(|d| format!("{:?}", d)).collect();
+            println!("{}", moves.join(" "));
+        }
+        println!("OPTIMAL COST:\t\t{}", all.cost);
+        println!(
+            "SOLUTIONS:\t\t{}{}",
+            all.nb_solutions,
+            if all.truncated { " (stopped)" } else { "" }
+        );
+        println!("COMPLEXITY IN SIZE:\t{}", all.complexity.in_size);
+        println!("COMPLEXITY IN TIME:\t{}", all.complexity.in_time);
+        return;
     }
-    match now.elapsed() {
-        Ok(elapsed) => {
-            println!(
-                "RESOLVED TIME:\t\t{} secondes and {} milisecondes",
-                elapsed.as_secs(),
-                elapsed.subsec_millis()
-            );
+
+    if algorithm == Algorithm::Reduction {
+        // the boards along the way would not fit in memory for big taquins
+        let moves = match solver.moves(&taquin) {
+            Ok(moves) => moves,
+            Err(SolveError::Unsolvable) => {
+                println!("this is unsolvable");
+                return;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                ::std::process::exit(1);
+            }
+        };
+        let moves: Vec<String> = moves.iter().map(|d| format!("{:?}", d)).collect();
+        println!("MOVES: {}", moves.join(" "));
+        print_elapsed(now);
+        println!("NB MOVES:\t\t{}", moves.len());
+        return;
+    }
+
+    let sol = match end_progress(&matches, solver.solve(&taquin)) {
+        Ok(sol) => sol,
+        Err(SolveError::Unsolvable) => {
+            println!("this is unsolvable");
+            return;
         }
         Err(e) => {
-            println!("Error: {}", e);
+            eprintln!("{}", e);
+            ::std::process::exit(1);
         }
+    };
+    println!("PATH: ");
+    for p in &sol.path {
+        println!("{}", p);
     }
-    println!("COMPLEXITY IN SIZE:\t{}", sol.1.in_size);
-    println!("COMPLEXITY IN TIME:\t{}", sol.1.in_time);
-    println!("PATH LEN:\t\t{}", sol.0.len());
+    print_elapsed(now);
+    println!("COMPLEXITY IN SIZE:\t{}", sol.complexity.in_size);
+    println!("COMPLEXITY IN TIME:\t{}", sol.complexity.in_time);
+    println!("PATH LEN:\t\t{}", sol.path.len());
 	match matches.opt_str("u") {
 		Some(username) => {
 			use ::std::process::Command;
@@ -266,14 +467,14 @@ fn main() {
 					::std::process::exit(1);
 				}
 			};
-			if let Err(_) = visualize_path(sol.0, user_image_path.trim(), &spiral, true) {
+			if let Err(_) = visualize_path(sol.path, user_image_path.trim(), &spiral, &*heuristique, true) {
 				std::process::exit(1);
 			}
 		}
 		None => {
 			match matches.opt_str("v") {
 				Some(image_path) => {
-					if let Err(_) = visualize_path(sol.0, image_path, &spiral, false) {
+					if let Err(_) = visualize_path(sol.path, image_path, &spiral, &*heuristique, false) {
 						std::process::exit(1);
 					}
 				},
@@ -286,6 +487,10 @@ fn main() {
 #[cfg(test)]
 mod test {
     use super::*;
+    use npuzzle::astar;
+    use npuzzle::idastar;
+    use npuzzle::pruning::PruningAutomaton;
+    use npuzzle::search_problem::TaquinProblem;
     use std::process::Command;
     use std::str::from_utf8;
     const NB_TEST: usize = 5;
@@ -299,8 +504,7 @@ mod test {
                 .expect("failed to execute process");
             let s = from_utf8(&output.stdout).unwrap();
 
-            let automaton: Trie =
-                deserialize(&fs::read("prunning_automaton_3x3_d10.serde").unwrap()[..]).unwrap();
+            let automaton = PruningAutomaton::load("prunning_automaton_3x3_d10.serde").unwrap();
             let taquin = s.parse::<Taquin>().unwrap();
             let spiral = Taquin::spiral(taquin.dim());
             if !taquin.is_solvable(&spiral) {
@@ -308,28 +512,12 @@ mod test {
                 return;
             }
 
+            let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+            let pruned_problem =
+                TaquinProblem::new(spiral.clone(), &Manhattan).with_automaton(&automaton.trie);
             assert_eq!(
-                idastar(
-                    &taquin,
-                    |t| t.sorted_neighbours(&spiral).into_iter().zip(repeat(1)),
-                    |t, a| t.move_piece(a).unwrap(),
-                    |t| t.manhattan_heuristic(&spiral),
-                    |t| t.is_solved(&spiral),
-                    TrieType::Match(0),
-                    |old_state, dir| automaton.change_true_state(old_state, dir),
-                    |t| *t == TrieType::Redundant,
-                ).unwrap()
-                    .0
-                    .len(),
-                astar(
-                    &taquin,
-                    |t| t.neighbours().into_iter().zip(repeat(1)),
-                    |t, a| t.move_piece(a).unwrap(),
-                    |t| t.manhattan_heuristic(&spiral),
-                    |t| t.is_solved(&spiral),
-                ).unwrap()
-                    .0
-                    .len()
+                idastar::search(&pruned_problem, &taquin).unwrap().0.len(),
+                astar::search(&problem, &taquin).unwrap().0.len()
             );
         }
     }
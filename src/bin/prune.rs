@@ -1,20 +1,70 @@
 extern crate getopts;
 use getopts::Options;
 use std::env;
+use std::fs;
 extern crate npuzzle;
-use npuzzle::construct_pruning_trie::construct_pruning_trie;
-use npuzzle::trie::*;
+use npuzzle::construct_pruning_trie::{build_pruning_trie, MAX_LAYERED_DEPTH};
+use npuzzle::flat_trie::FlatTrie;
+use npuzzle::pruning::{normalized_dim, AutomatonFileError, PruningAutomaton};
 use std::str::FromStr;
-extern crate bincode;
-use bincode::{deserialize, serialize};
-use std::fs::File;
-use std::io::Write;
+use std::time::Instant;
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options]", program);
+    let brief = format!(
+        "Usage: {} [options]\n       {} inspect FILE [options]",
+        program, program
+    );
     print!("{}", opts.usage(&brief));
 }
 
+/// Print statistics about the automaton in `path`, serde or flat, and export
+/// it if asked
+fn inspect(path: &str, max_len: Option<usize>, dot: Option<String>, dot_depth: usize) {
+    let automaton = match FlatTrie::open(path) {
+        Ok(flat) => flat.verify().map(|_| flat.to_automaton()),
+        Err(AutomatonFileError::BadMagic) => PruningAutomaton::load(path),
+        Err(e) => Err(e),
+    };
+    let automaton = match automaton {
+        Ok(automaton) => automaton,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            ::std::process::exit(1);
+        }
+    };
+    let trie = &automaton.trie;
+    println!(
+        "built for {}x{}, depth {}",
+        automaton.dim, automaton.dim, automaton.depth
+    );
+    println!("nodes: {}", trie.0.len());
+    println!("redundant transitions: {}", trie.nb_redundant());
+
+    let depths = trie.depths();
+    let mut nb_by_depth = vec![0; depths.iter().max().map_or(0, |d| d + 1)];
+    for &d in depths.iter() {
+        nb_by_depth[d] += 1;
+    }
+    println!("nodes by depth:");
+    for (d, nb) in nb_by_depth.iter().enumerate() {
+        println!("  {:>3}: {}", d, nb);
+    }
+
+    println!("pruned move sequences by length:");
+    let max_len = max_len.unwrap_or(automaton.depth);
+    for (len, fraction) in trie.pruned_fractions(max_len).iter().enumerate() {
+        println!("  {:>3}: {:.4}%", len + 1, fraction * 100.);
+    }
+
+    if let Some(dot) = dot {
+        if let Err(e) = fs::write(&dot, trie.to_dot(dot_depth)) {
+            eprintln!("{}: {}", dot, e);
+            ::std::process::exit(1);
+        }
+        println!("graph up to depth {} written to {}", dot_depth, dot);
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
@@ -23,15 +73,73 @@ fn main() {
     opts.optopt("o", "", "set output file name", "NAME");
     opts.optopt("s", "", "size of the taquin", "INTEGER");
     opts.optopt("d", "", "stop at depth", "INTEGER");
+    opts.optopt("j", "threads", "threads expanding each layer", "INTEGER");
+    opts.optopt(
+        "",
+        "migrate",
+        "convert a raw automaton built for -s and -d to the current format",
+        "PATH",
+    );
+    opts.optopt(
+        "k",
+        "",
+        "inspect: longest move sequences to count, the depth by default",
+        "INTEGER",
+    );
+    opts.optopt("", "dot", "inspect: export the automaton as graphviz", "PATH");
+    opts.optopt(
+        "",
+        "dot-depth",
+        "inspect: deepest nodes exported, 3 by default",
+        "INTEGER",
+    );
+    opts.optflag("m", "minimise", "minimise the automaton before saving it");
+    opts.optflag(
+        "",
+        "flat",
+        "save in the flat layout, memory-mapped by npuzzle instead of deserialised",
+    );
     opts.optflag("h", "help", "print this help menu");
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
-        Err(f) => panic!(f.to_string()),
+        Err(f) => {
+            eprintln!("{}", f);
+            print_usage(&program, opts);
+            ::std::process::exit(1);
+        }
     };
     if matches.opt_present("h") {
         print_usage(&program, opts);
         return;
     }
+    if matches.free.first().map(|s| s.as_str()) == Some("inspect") {
+        let path = match matches.free.get(1) {
+            Some(path) => path,
+            None => {
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        };
+        let max_len = match matches.opt_str("k").map(|k| usize::from_str(&k)) {
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+            k => k.map(|k| k.unwrap()),
+        };
+        let dot_depth = matches.opt_str("dot-depth").unwrap_or("3".to_string());
+        let dot_depth = match usize::from_str(&dot_depth) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("{}", e);
+                print_usage(&program, opts);
+                ::std::process::exit(1);
+            }
+        };
+        inspect(path, max_len, matches.opt_str("dot"), dot_depth);
+        return;
+    }
     let size = match usize::from_str(&matches.opt_str("s").unwrap_or("4".to_string()))  {
 		Ok(size) => size,
 		Err(e) => {
@@ -54,47 +162,88 @@ fn main() {
             ::std::process::exit(1);
 		}
 	};
-    let output = matches.opt_str("o").unwrap_or(format!(
-        "prunning_automaton_{}x{}_d{}.serde",
-        size, size, depth
-    ));
-    println!("size {}, depth {}, output {}", size, depth, output);
-    let (trie, _, _) = construct_pruning_trie(size, depth);
-    let encoded: Vec<u8> = match serialize(&trie)  {
-		Ok(file) => file,
-		Err(e) => {
-            eprintln!("{}", e);
-            print_usage(&program, opts);
-            ::std::process::exit(1);
-		}
-	};
-    let mut f = match File::create(output)  {
-		Ok(file) => file,
-		Err(e) => {
-            eprintln!("{}", e);
+    if depth > MAX_LAYERED_DEPTH {
+        eprintln!("Invalid depth specified: {}, at most {}", depth, MAX_LAYERED_DEPTH);
+        ::std::process::exit(1);
+    }
+    let threads = match usize::from_str(&matches.opt_str("j").unwrap_or("1".to_string())) {
+        Ok(threads) if threads > 0 => threads,
+        _ => {
+            eprintln!("Invalid number of threads");
             print_usage(&program, opts);
             ::std::process::exit(1);
-		}
-	};
-    match f.write(&encoded) {
-		Ok(_) => (),
-		Err(e) => {
+        }
+    };
+    let output = matches.opt_str("o").unwrap_or(format!(
+        "prunning_automaton_{}x{}_d{}.{}",
+        size,
+        size,
+        depth,
+        if matches.opt_present("flat") { "flat" } else { "serde" }
+    ));
+    let mut automaton = match matches.opt_str("migrate") {
+        Some(old) => {
+            println!("migrate {} (size {}, depth {}) to {}", old, size, depth, output);
+            match PruningAutomaton::migrate(old, size, depth) {
+                Ok(automaton) => automaton,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            }
+        }
+        None => {
+            println!("size {}, depth {}, output {}", size, depth, output);
+            let start = Instant::now();
+            let trie = build_pruning_trie(size, depth, threads, |layer| {
+                eprintln!(
+                    "depth {:>2}: {} new boards, {} redundant paths, {} nodes ({:.1}s)",
+                    layer.depth,
+                    layer.nb_states,
+                    layer.nb_redundant,
+                    layer.nb_nodes,
+                    start.elapsed().as_secs_f64()
+                )
+            });
+            let trie = match trie {
+                Ok(trie) => trie,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            };
+            PruningAutomaton {
+                dim: normalized_dim(size, depth),
+                depth,
+                trie,
+            }
+        }
+    };
+    if matches.opt_present("m") {
+        let nb_nodes = automaton.trie.0.len();
+        automaton.trie = automaton.trie.minimise();
+        println!("minimised from {} nodes", nb_nodes);
+    }
+    if matches.opt_present("flat") {
+        if let Err(e) = FlatTrie::write(&automaton, &output) {
             eprintln!("{}", e);
-            print_usage(&program, opts);
             ::std::process::exit(1);
-		}
-	}
-    // 8 bytes for the length of the vector, 4 bytes per float.
-    println!("{} | {}", encoded.len(), trie.0.len());
+        }
+        println!("{} nodes", automaton.trie.0.len());
+        return;
+    }
+    if let Err(e) = automaton.save(&output) {
+        eprintln!("{}", e);
+        ::std::process::exit(1);
+    }
+    println!("{} nodes", automaton.trie.0.len());
 
-    let decoded: Trie = match deserialize(&encoded[..]) {
-		Ok(file) => file,
-		Err(e) => {
+    let decoded = match PruningAutomaton::load(&output) {
+        Ok(automaton) => automaton,
+        Err(e) => {
             eprintln!("{}", e);
-            print_usage(&program, opts);
             ::std::process::exit(1);
-		}
-	};
-
-    assert_eq!(trie, decoded);
+        }
+    };
+    assert_eq!(automaton, decoded);
 }
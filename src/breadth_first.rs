@@ -0,0 +1,117 @@
+use complexity::Complexity;
+use search_problem::SearchProblem;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use zobrist::BuildZobristHasher;
+
+/// Breadth first search, optimal when every action costs the same. The goal
+/// is tested when a state is generated, one layer earlier than astar would.
+pub fn search<P>(problem: &P, start: &P::State) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+{
+    const DEFAULT_SEEN_SET_SIZE: usize = 0x1_0000;
+
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 1,
+    };
+    // every generated state with the index of its predecessor
+    let mut nodes: Vec<(P::State, Option<usize>)> = vec![(start.clone(), None)];
+    let unwind = |nodes: &[(P::State, Option<usize>)], index: usize| {
+        let mut path = Vec::new();
+        let mut index = Some(index);
+        while let Some(i) = index {
+            path.push(nodes[i].0.clone());
+            index = nodes[i].1;
+        }
+        path
+    };
+    if problem.is_goal(start) {
+        return Some((unwind(&nodes, 0), complexity));
+    }
+    let mut seen =
+        HashSet::with_capacity_and_hasher(DEFAULT_SEEN_SET_SIZE, BuildZobristHasher::default());
+    seen.insert(start.clone());
+    let mut open_set = VecDeque::new();
+    open_set.push_back(0);
+    while let Some(current) = open_set.pop_front() {
+        if problem.interrupted() {
+            return None;
+        }
+        problem.progress(None, open_set.len());
+        complexity.in_time += 1;
+        for (action, _) in problem.successors(&nodes[current].0) {
+            let state = problem.apply(&nodes[current].0, action);
+            if !seen.insert(state.clone()) {
+                continue;
+            }
+            let is_goal = problem.is_goal(&state);
+            nodes.push((state, Some(current)));
+            if is_goal {
+                complexity.in_size = nodes.len();
+                return Some((unwind(&nodes, nodes.len() - 1), complexity));
+            }
+            open_set.push_back(nodes.len() - 1);
+        }
+    }
+    None
+}
+
+/// Number of actions from `start` to every state reachable in at most
+/// `max_depth` of them. From the goal of a problem whose actions are
+/// reversible, this is the exact distance of every state to the goal.
+pub fn distances<P>(
+    problem: &P,
+    start: &P::State,
+    max_depth: Option<usize>,
+) -> HashMap<P::State, usize, BuildZobristHasher>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+{
+    let mut distances = HashMap::default();
+    let mut open_set = VecDeque::new();
+    distances.insert(start.clone(), 0);
+    open_set.push_back((start.clone(), 0));
+    while let Some((state, distance)) = open_set.pop_front() {
+        if max_depth.map_or(false, |max_depth| distance >= max_depth) {
+            continue;
+        }
+        for (action, _) in problem.successors(&state) {
+            let next = problem.apply(&state, action);
+            if !distances.contains_key(&next) {
+                distances.insert(next.clone(), distance + 1);
+                open_set.push_back((next, distance + 1));
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use heuristic::Zero;
+    use search_problem::test::assert_optimal_like_astar;
+    use search_problem::TaquinProblem;
+    use taquin::Taquin;
+
+    #[test]
+    fn optimal() {
+        assert_optimal_like_astar(|problem, taquin| {
+            let uninformed = TaquinProblem::new(problem.goal().clone(), &Zero);
+            search(&uninformed, taquin).map(|(path, _)| path)
+        });
+    }
+    #[test]
+    fn bounded_distances() {
+        let spiral = Taquin::spiral(3);
+        let distances = distances(&TaquinProblem::new(spiral.clone(), &Zero), &spiral, Some(2));
+        // the blank of the spiral goal is in the center: 4 boards at 1 move,
+        // 8 at 2 moves
+        assert_eq!(distances.len(), 1 + 4 + 8);
+        assert_eq!(distances.values().max(), Some(&2));
+    }
+}
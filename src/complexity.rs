@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Complexity {
     pub in_time: usize,
     pub in_size: usize,
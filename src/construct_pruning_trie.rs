@@ -1,9 +1,11 @@
 use maxdir::MaxDir;
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::cmp::{min, Ordering};
+use std::collections::{HashMap, HashSet};
 use std::collections::VecDeque;
+use std::thread;
 use taquin::{Dir, Taquin};
-use trie::Trie;
+use trie::{Trie, TrieError};
+use zobrist::BuildZobristHasher;
 
 const DEFAULT_CLOSED_SET_SIZE: usize = 0x1_0000;
 const DEFAULT_OPEN_SET_SIZE: usize = 0x1_0000;
@@ -52,10 +54,11 @@ impl Node {
 pub fn construct_pruning_trie(
     taquin_dim: usize,
     depth: usize,
-) -> (Trie, Vec<Vec<Dir>>, Vec<Vec<Dir>>) {
-    let spiral = Taquin::spiral(2 * taquin_dim - 1);
-    let mut closed_set: HashMap<Taquin, Vec<Node>> =
-        HashMap::with_capacity(DEFAULT_CLOSED_SET_SIZE);
+) -> Result<(Trie, Vec<Vec<Dir>>, Vec<Vec<Dir>>), TrieError> {
+    // the blank starts at the center and never goes further than `depth`
+    let spiral = Taquin::spiral(min(2 * taquin_dim - 1, 2 * depth + 1));
+    let mut closed_set: HashMap<Taquin, Vec<Node>, BuildZobristHasher> =
+        HashMap::with_capacity_and_hasher(DEFAULT_CLOSED_SET_SIZE, Default::default());
     let mut open_set = VecDeque::with_capacity(DEFAULT_OPEN_SET_SIZE);
     let init_node = Node::new(Vec::new(), spiral.clone(), MaxDir::new([0; 4], 0, 0));
     let mut trie = Trie::new();
@@ -78,7 +81,7 @@ pub fn construct_pruning_trie(
                         .push(neighbour_node.clone());
                     //println!("\redundant {:?}", neighbour_node.path);
 
-                    trie.add_word(&neighbour_node.path, false);
+                    trie.add_word(&neighbour_node.path, false)?;
                     //    debug_assert!(trie.match_word(neighbour_node.path.iter()) == TrieType::Redundant);
 
                     redundant_paths.push(neighbour_node.path);
@@ -92,5 +95,188 @@ pub fn construct_pruning_trie(
         }
     }
     trie.update_failure();
-    (trie, redundant_paths, primitive_paths)
+    Ok((trie, redundant_paths, primitive_paths))
+}
+
+/// Deepest automaton `build_pruning_trie` can build, paths are packed in a u64
+pub const MAX_LAYERED_DEPTH: usize = 32;
+
+/// Number of boards expanded at once, bounding the memory used by the
+/// children waiting to be deduplicated
+const BLOCK_SIZE: usize = 0x1_0000;
+
+const DIRS: [Dir; 4] = [Dir::Right, Dir::Up, Dir::Down, Dir::Left];
+
+/// Board reached from the start: tiles are named after their starting cell and
+/// only the cells whose tile moved are stored, sorted by cell
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct SparseBoard {
+    blank: u32,
+    moved: Vec<(u32, u32)>,
+}
+
+impl SparseBoard {
+    fn tile(&self, cell: u32) -> u32 {
+        match self.moved.binary_search_by_key(&cell, |&(c, _)| c) {
+            Ok(i) => self.moved[i].1,
+            Err(_) => cell,
+        }
+    }
+    fn set(&mut self, cell: u32, tile: u32) {
+        match self.moved.binary_search_by_key(&cell, |&(c, _)| c) {
+            Ok(i) if tile == cell => {
+                self.moved.remove(i);
+            }
+            Ok(i) => self.moved[i].1 = tile,
+            Err(_) if tile == cell => (),
+            Err(i) => self.moved.insert(i, (cell, tile)),
+        }
+    }
+    /// Move the blank toward `dir` on a `width` x `width` board whose center
+    /// is `center`
+    fn move_blank(&self, dir: Dir, width: u32, center: u32) -> Option<Self> {
+        let (row, col) = (self.blank / width, self.blank % width);
+        let target = match dir {
+            Dir::Right if col + 1 < width => self.blank + 1,
+            Dir::Down if row + 1 < width => self.blank + width,
+            Dir::Left if col > 0 => self.blank - 1,
+            Dir::Up if row > 0 => self.blank - width,
+            _ => return None,
+        };
+        let mut next = self.clone();
+        next.set(self.blank, self.tile(target));
+        next.set(target, center);
+        next.blank = target;
+        Some(next)
+    }
+}
+
+fn unpack_path(path: u64, len: usize) -> Vec<Dir> {
+    (0..len)
+        .map(|i| Dir::from((path >> (2 * i) & 3) as usize))
+        .collect()
+}
+
+/// Children of `frontier`, in the order a breadth first search finds them
+fn expand(frontier: &[(SparseBoard, u64)], len: usize, width: u32) -> Vec<(SparseBoard, u64)> {
+    let center = width * width / 2;
+    let mut children = Vec::with_capacity(frontier.len() * 3);
+    for &(ref board, path) in frontier.iter() {
+        for &d in DIRS.iter() {
+            if let Some(child) = board.move_blank(d, width, center) {
+                children.push((child, path | (d as u64) << (2 * len)));
+            }
+        }
+    }
+    children
+}
+
+/// State of `build_pruning_trie` after each layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerProgress {
+    pub depth: usize,
+    /// Boards first reached at this depth
+    pub nb_states: usize,
+    /// Paths of this depth reaching an already known board
+    pub nb_redundant: usize,
+    pub nb_nodes: usize,
+}
+
+/// Same automaton as `construct_pruning_trie`, built layer by layer.
+///
+/// The moves graph is bipartite so the children of a layer are either in the
+/// previous layer or in the next one: only these two layers and the frontier
+/// are kept, with the paths packed on 2 bits per move. The children are
+/// computed on `threads` threads and merged in order, so the automaton does
+/// not depend on the number of threads. `progress` is called after each
+/// layer.
+pub fn build_pruning_trie<F: FnMut(&LayerProgress)>(
+    taquin_dim: usize,
+    depth: usize,
+    threads: usize,
+    mut progress: F,
+) -> Result<Trie, TrieError> {
+    if depth > MAX_LAYERED_DEPTH {
+        return Err(TrieError::DepthTooBig {
+            depth,
+            max: MAX_LAYERED_DEPTH,
+        });
+    }
+    let width = min(2 * taquin_dim - 1, 2 * depth + 1) as u32;
+    let threads = threads.max(1);
+    let start = SparseBoard {
+        blank: width * width / 2,
+        moved: Vec::new(),
+    };
+    let mut trie = Trie::new();
+    let mut previous: HashSet<SparseBoard, BuildZobristHasher> = HashSet::default();
+    let mut frontier = vec![(start, 0)];
+
+    for len in 0..depth {
+        let last = len + 1 == depth;
+        let mut next_set: HashSet<SparseBoard, BuildZobristHasher> = HashSet::default();
+        let mut next = Vec::new();
+        let mut nb_redundant = 0;
+        for block in frontier.chunks(BLOCK_SIZE) {
+            let chunk_size = (block.len() + threads - 1) / threads;
+            let children: Vec<Vec<(SparseBoard, u64)>> = if threads == 1 {
+                vec![expand(block, len, width)]
+            } else {
+                thread::scope(|scope| {
+                    let handles: Vec<_> = block
+                        .chunks(chunk_size)
+                        .map(|chunk| scope.spawn(move || expand(chunk, len, width)))
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|h| h.join().map_err(|_| TrieError::WorkerPanicked))
+                        .collect::<Result<_, _>>()
+                })?
+            };
+            for (board, path) in children.into_iter().flat_map(|c| c) {
+                if previous.contains(&board) || next_set.contains(&board) {
+                    trie.add_word(&unpack_path(path, len + 1), false)?;
+                    nb_redundant += 1;
+                } else {
+                    if !last {
+                        next.push((board.clone(), path));
+                    }
+                    next_set.insert(board);
+                }
+            }
+        }
+        progress(&LayerProgress {
+            depth: len + 1,
+            nb_states: next_set.len(),
+            nb_redundant,
+            nb_nodes: trie.0.len(),
+        });
+        previous = frontier.into_iter().map(|(board, _)| board).collect();
+        frontier = next;
+    }
+    trie.update_failure();
+    Ok(trie)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn layered() {
+        for &(dim, depth) in [(2, 7), (3, 8), (4, 7), (6, 6)].iter() {
+            let mut layers = Vec::new();
+            let trie = build_pruning_trie(dim, depth, 1, |p| layers.push(*p)).unwrap();
+            assert_eq!(trie, construct_pruning_trie(dim, depth).unwrap().0);
+            assert_eq!(layers.len(), depth);
+            assert_eq!(layers[0].nb_redundant, 0);
+            assert_eq!(build_pruning_trie(dim, depth, 3, |_| ()), Ok(trie));
+        }
+        assert_eq!(
+            build_pruning_trie(3, MAX_LAYERED_DEPTH + 1, 1, |_| ()),
+            Err(TrieError::DepthTooBig {
+                depth: MAX_LAYERED_DEPTH + 1,
+                max: MAX_LAYERED_DEPTH
+            })
+        );
+    }
 }
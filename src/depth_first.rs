@@ -0,0 +1,105 @@
+use num_traits::Zero;
+use std::cmp::{Ord};
+use std::collections::HashSet;
+use std::fmt::{Debug, Display};
+use std::hash::{Hash};
+use complexity::Complexity;
+use zobrist::BuildZobristHasher;
+use search_problem::{never_redundant, no_pruning, Closures, SearchProblem};
+
+/// Depth first walk trying the successors by increasing heuristic, the
+/// first one not seen yet is followed and the walk backtracks when stuck.
+/// Fast on easy boards but the path found can be very long.
+pub fn depth_first_search<N, C, FN, IN, FH, FS, FA, A>(
+    start: &N,
+    neighbours_actions: FN,
+    perform_action: FA,
+    heuristic: FH,
+    success: FS,
+) -> Option<(Vec<N>, Complexity)>
+where
+    N: Clone + Hash + Eq + Debug + Display,
+    C: Zero + Ord + Copy + Debug,
+    FN: Fn(&N) -> IN,
+    IN: IntoIterator<Item = (A, C)>,
+    FH: Fn(&N) -> C,
+    FS: Fn(&N) -> bool,
+    FA: Fn(&N, A) -> N,
+    A: Copy,
+{
+    search(
+        &Closures::new(
+            neighbours_actions,
+            perform_action,
+            heuristic,
+            success,
+            (),
+            no_pruning,
+            never_redundant,
+        ),
+        start,
+    )
+}
+
+pub fn search<P>(problem: &P, start: &P::State) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq + Debug + Display,
+{
+    const DEFAULT_CLOSED_SET_SIZE: usize = 0x1_0000;
+	
+    let mut complexity = Complexity { in_time : 0, in_size : 0};
+    let mut closed_set =
+        HashSet::with_capacity_and_hasher(DEFAULT_CLOSED_SET_SIZE, BuildZobristHasher::default());
+
+	let mut current_state = start.clone();
+	let mut paths = Vec::new();
+	'blocked: while !problem.is_goal(&current_state) {
+		if problem.interrupted() {
+			return None;
+		}
+		complexity.in_time += 1;
+		let mut next_states: Vec<P::State> = problem
+			.successors(&current_state)
+			.into_iter()
+			.map(|(action, _)| problem.apply(&current_state, action))
+			.collect();
+		next_states.sort_by_key(|state| problem.heuristic(state));
+		for next_state in next_states {
+			if closed_set.insert(next_state.clone()) == false {
+				continue
+			}
+			paths.push(current_state);
+			current_state = next_state;
+			continue 'blocked
+		}
+		// None once every reachable state was seen
+		current_state = paths.pop()?;
+	}
+	complexity.in_size = closed_set.len();
+	paths.push(current_state);
+	paths.reverse();
+    Some((paths, complexity))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use heuristic::Manhattan;
+    use search_problem::TaquinProblem;
+    use taquin::Taquin;
+
+    #[test]
+    fn depth_first() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![1, 2, 3, 0, 8, 4, 7, 6, 5]);
+        let (path, _) = search(&TaquinProblem::new(spiral.clone(), &Manhattan), &taquin).unwrap();
+        assert_eq!(path, vec![spiral, taquin]);
+    }
+    #[test]
+    fn unsolvable() {
+        let taquin = Taquin::new(2, vec![2, 1, 3, 0]);
+        let problem = TaquinProblem::new(Taquin::spiral(2), &Manhattan);
+        assert!(search(&problem, &taquin).is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use complexity::Complexity;
+use num_traits::Zero;
+use search_problem::SearchProblem;
+use std::collections::HashMap;
+use std::hash::Hash;
+use zobrist::BuildZobristHasher;
+
+/// Monotone priority queue with one bucket per cost (Dial's algorithm).
+/// Costs must be small integers since every bucket up to the greatest one
+/// pushed is allocated.
+struct BucketQueue<T> {
+    buckets: Vec<Vec<T>>,
+    /// No bucket before it holds anything
+    current: usize,
+    len: usize,
+}
+
+impl<T> BucketQueue<T> {
+    fn new() -> Self {
+        BucketQueue {
+            buckets: Vec::new(),
+            current: 0,
+            len: 0,
+        }
+    }
+
+    /// `cost` cannot be lower than the cost of the last popped item
+    fn push(&mut self, cost: usize, item: T) {
+        debug_assert!(cost >= self.current);
+        if cost >= self.buckets.len() {
+            self.buckets.resize_with(cost + 1, Vec::new);
+        }
+        self.buckets[cost].push(item);
+        self.len += 1;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn pop(&mut self) -> Option<(usize, T)> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.buckets[self.current].is_empty() {
+            self.current += 1;
+        }
+        self.len -= 1;
+        let item = self.buckets[self.current].pop().unwrap();
+        Some((self.current, item))
+    }
+}
+
+/// Cost of the cheapest path to every node, `index` of a node in the nodes
+struct Best<S: Hash + Eq> {
+    costs: HashMap<S, (u64, usize), BuildZobristHasher>,
+}
+
+impl<S: Hash + Eq + Clone> Best<S> {
+    /// Record `cost` for `state` if it is cheaper than the known one
+    fn improve(&mut self, state: &S, cost: u64, index: usize) -> bool {
+        match self.costs.get(state) {
+            Some(&(known, _)) if known <= cost => false,
+            _ => {
+                self.costs.insert(state.clone(), (cost, index));
+                true
+            }
+        }
+    }
+}
+
+/// Dijkstra's algorithm on a bucket queue: optimal whatever the cost of the
+/// actions as long as they are small integers, the heuristic is ignored.
+pub fn search<P>(problem: &P, start: &P::State) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+    P::Cost: Into<u64>,
+{
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 0,
+    };
+    // every generated state with the index of its predecessor
+    let mut nodes: Vec<(P::State, Option<usize>)> = vec![(start.clone(), None)];
+    let mut best = Best {
+        costs: HashMap::default(),
+    };
+    let mut open_set = BucketQueue::new();
+    best.improve(start, 0, 0);
+    open_set.push(0, 0);
+    while let Some((cost, current)) = open_set.pop() {
+        // a cheaper path to this state was found after it was pushed
+        if best.costs[&nodes[current].0].1 != current {
+            continue;
+        }
+        if problem.is_goal(&nodes[current].0) {
+            complexity.in_size = nodes.len();
+            let mut path = Vec::new();
+            let mut index = Some(current);
+            while let Some(i) = index {
+                path.push(nodes[i].0.clone());
+                index = nodes[i].1;
+            }
+            return Some((path, complexity));
+        }
+        if problem.interrupted() {
+            return None;
+        }
+        problem.progress(None, open_set.len());
+        complexity.in_time += 1;
+        for (action, action_cost) in problem.successors(&nodes[current].0) {
+            let state = problem.apply(&nodes[current].0, action);
+            let next_cost = cost as u64 + action_cost.into();
+            if best.improve(&state, next_cost, nodes.len()) {
+                open_set.push(next_cost as usize, nodes.len());
+                nodes.push((state, Some(current)));
+            }
+        }
+    }
+    None
+}
+
+/// Cost of the cheapest path from `start` to every reachable state
+pub fn distances<P>(problem: &P, start: &P::State) -> HashMap<P::State, P::Cost, BuildZobristHasher>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+    P::Cost: Into<u64>,
+{
+    let mut distances: HashMap<P::State, P::Cost, BuildZobristHasher> = HashMap::default();
+    let mut open_set = BucketQueue::new();
+    distances.insert(start.clone(), P::Cost::zero());
+    open_set.push(0, (start.clone(), P::Cost::zero()));
+    while let Some((_, (state, cost))) = open_set.pop() {
+        if distances[&state] < cost {
+            continue;
+        }
+        for (action, action_cost) in problem.successors(&state) {
+            let next = problem.apply(&state, action);
+            let next_cost = cost + action_cost;
+            if distances
+                .get(&next)
+                .map_or(true, |&known| next_cost < known)
+            {
+                distances.insert(next.clone(), next_cost);
+                open_set.push(next_cost.into() as usize, (next, next_cost));
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use breadth_first;
+    use heuristic::Zero;
+    use search_problem::test::assert_optimal_like_astar;
+    use search_problem::{never_redundant, no_pruning, Closures, TaquinProblem};
+    use taquin::Taquin;
+
+    #[test]
+    fn optimal() {
+        assert_optimal_like_astar(|problem, taquin| {
+            let uninformed = TaquinProblem::new(problem.goal().clone(), &Zero);
+            search(&uninformed, taquin).map(|(path, _)| path)
+        });
+    }
+    #[test]
+    fn weighted() {
+        // from 1 to 10 by adding 1 for 1 or doubling for 3: adding up to 5
+        // and doubling costs 7, fewer actions like 1 +1 2 *2 4 +1 5 *2 10
+        // cost 8
+        let problem = Closures::new(
+            |_: &u64| vec![(false, 1u64), (true, 3)],
+            |&n: &u64, double| if double { 2 * n } else { n + 1 },
+            |_: &u64| 0u64,
+            |&n: &u64| n == 10,
+            (),
+            no_pruning,
+            never_redundant,
+        );
+        let (path, _) = search(&problem, &1).unwrap();
+        assert_eq!(path, vec![10, 5, 4, 3, 2, 1]);
+    }
+    #[test]
+    fn same_distances_as_breadth_first() {
+        let spiral = Taquin::spiral(2);
+        let problem = TaquinProblem::new(spiral.clone(), &Zero);
+        let dijkstra = distances(&problem, &spiral);
+        let bfs = breadth_first::distances(&problem, &spiral, None);
+        assert_eq!(dijkstra.len(), 12);
+        assert!(bfs.iter().all(|(t, &d)| dijkstra[t] == d as u64));
+    }
+}
@@ -0,0 +1,446 @@
+use bincode::{deserialize, serialize};
+use rank::{partial_rank, partial_unrank};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use taquin::Taquin;
+
+/// Keys sorted in memory before being written as a run
+pub const DEFAULT_RUN_LEN: usize = 1 << 24;
+
+const CHECKPOINT: &str = "checkpoint";
+
+#[derive(Debug)]
+pub enum DiskBfsError {
+    Io(io::Error),
+    Checkpoint(::bincode::Error),
+    /// The directory holds a search of another space
+    CheckpointMismatch,
+    /// A tile is repeated, is the blank or is not on the board
+    BadPattern(u64),
+    /// The keys of the space do not fit in a u64
+    TooBig,
+}
+
+impl fmt::Display for DiskBfsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiskBfsError::Io(ref e) => write!(f, "{}", e),
+            DiskBfsError::Checkpoint(ref e) => write!(f, "bad checkpoint: {}", e),
+            DiskBfsError::CheckpointMismatch => {
+                write!(f, "the directory holds the search of another space")
+            }
+            DiskBfsError::BadPattern(tile) => write!(f, "bad tile in the pattern: {}", tile),
+            DiskBfsError::TooBig => write!(f, "too many boards to rank them in a u64"),
+        }
+    }
+}
+
+impl Error for DiskBfsError {
+    fn description(&self) -> &str {
+        match *self {
+            DiskBfsError::Io(_) => "io error",
+            DiskBfsError::Checkpoint(_) => "bad checkpoint",
+            DiskBfsError::CheckpointMismatch => "checkpoint mismatch",
+            DiskBfsError::BadPattern(_) => "bad pattern",
+            DiskBfsError::TooBig => "space too big",
+        }
+    }
+}
+
+impl From<io::Error> for DiskBfsError {
+    fn from(e: io::Error) -> Self {
+        DiskBfsError::Io(e)
+    }
+}
+
+/// Written once a layer is complete, the search resumes from the last one
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Checkpoint {
+    n: usize,
+    /// Blank then pattern tiles, their positions make the keys
+    tiles: Vec<u64>,
+    start: u64,
+    /// Number of boards at each depth so far
+    counts: Vec<u64>,
+}
+
+/// Sorted keys of a file, in little endian
+struct Keys {
+    reader: BufReader<File>,
+    next: Option<u64>,
+}
+
+impl Keys {
+    fn open(path: &Path) -> io::Result<Self> {
+        let mut keys = Keys {
+            reader: BufReader::new(File::open(path)?),
+            next: None,
+        };
+        keys.advance()?;
+        Ok(keys)
+    }
+
+    fn advance(&mut self) -> io::Result<()> {
+        let mut bytes = [0; 8];
+        self.next = match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Some(u64::from_le_bytes(bytes)),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e),
+        };
+        Ok(())
+    }
+
+    /// Whether `key` is in the file, the keys below it are skipped
+    fn contains(&mut self, key: u64) -> io::Result<bool> {
+        while self.next.map_or(false, |next| next < key) {
+            self.advance()?;
+        }
+        Ok(self.next == Some(key))
+    }
+}
+
+fn write_keys<I: IntoIterator<Item = u64>>(path: &Path, keys: I) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for key in keys {
+        writer.write_all(&key.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Breadth first search of the boards of a dimension, or of their abstraction
+/// where only the blank and the tiles of a pattern are told apart, keeping the
+/// layers on disk: every layer is a file of sorted ranks. Successors are
+/// sorted in runs of bounded length, then merged and stripped of the two
+/// previous layers (delayed duplicate detection), so memory holds one run
+/// whatever the size of the space. A checkpoint is written after every
+/// layer, the search resumes from it.
+pub struct DiskBfs {
+    dir: PathBuf,
+    n: usize,
+    tiles: Vec<u64>,
+    start: Vec<usize>,
+    run_len: usize,
+    keep_layers: bool,
+    max_depth: Option<usize>,
+}
+
+impl DiskBfs {
+    /// Search from `goal` in `dir`, telling apart the blank and `pattern`,
+    /// every tile if it is empty
+    pub fn new<P: AsRef<Path>>(
+        dir: P,
+        goal: &Taquin,
+        pattern: &[u64],
+    ) -> Result<Self, DiskBfsError> {
+        let len = goal.dim() * goal.dim();
+        let mut tiles = vec![0];
+        if pattern.is_empty() {
+            tiles.extend(1..len as u64);
+        }
+        for &tile in pattern {
+            if tile == 0 || tile >= len as u64 || tiles.contains(&tile) {
+                return Err(DiskBfsError::BadPattern(tile));
+            }
+            tiles.push(tile);
+        }
+        if (len - tiles.len() + 1..len + 1)
+            .try_fold(1u64, |nb, k| nb.checked_mul(k as u64))
+            .is_none()
+        {
+            return Err(DiskBfsError::TooBig);
+        }
+        let start = tiles
+            .iter()
+            .map(|&tile| goal.iter().position(|&t| t == tile).unwrap())
+            .collect();
+        Ok(DiskBfs {
+            dir: dir.as_ref().to_path_buf(),
+            n: goal.dim(),
+            tiles,
+            start,
+            run_len: DEFAULT_RUN_LEN,
+            keep_layers: false,
+            max_depth: None,
+        })
+    }
+
+    /// Keys sorted in memory at once
+    pub fn run_len(mut self, run_len: usize) -> Self {
+        self.run_len = run_len.max(1);
+        self
+    }
+
+    /// Keep every layer instead of the last two only
+    pub fn keep_layers(mut self, keep_layers: bool) -> Self {
+        self.keep_layers = keep_layers;
+        self
+    }
+
+    /// Stop after this layer, the search can be resumed later
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// File of the boards at `depth`
+    pub fn layer_path(&self, depth: usize) -> PathBuf {
+        self.dir.join(format!("layer_{}", depth))
+    }
+
+    fn key(&self, positions: &[usize]) -> u64 {
+        partial_rank(positions, self.n * self.n)
+    }
+
+    /// Keys of the boards one move away from the board of `key`
+    fn successors(&self, key: u64, successors: &mut Vec<u64>) {
+        let n = self.n;
+        let mut positions = partial_unrank(self.tiles.len(), n * n, key);
+        let blank = positions[0];
+        let (row, col) = (blank / n, blank % n);
+        let mut cells = Vec::with_capacity(4);
+        if row > 0 {
+            cells.push(blank - n);
+        }
+        if row + 1 < n {
+            cells.push(blank + n);
+        }
+        if col > 0 {
+            cells.push(blank - 1);
+        }
+        if col + 1 < n {
+            cells.push(blank + 1);
+        }
+        for cell in cells {
+            // a tile of the pattern there takes the place of the blank
+            let moved = positions.iter().position(|&p| p == cell);
+            positions[0] = cell;
+            if let Some(tile) = moved {
+                positions[tile] = blank;
+            }
+            successors.push(self.key(&positions));
+            if let Some(tile) = moved {
+                positions[tile] = cell;
+            }
+            positions[0] = blank;
+        }
+    }
+
+    fn save_checkpoint(&self, counts: &[u64]) -> Result<(), DiskBfsError> {
+        let checkpoint = Checkpoint {
+            n: self.n,
+            tiles: self.tiles.clone(),
+            start: self.key(&self.start),
+            counts: counts.to_vec(),
+        };
+        let tmp = self.dir.join(format!("{}.tmp", CHECKPOINT));
+        fs::write(
+            &tmp,
+            serialize(&checkpoint).map_err(DiskBfsError::Checkpoint)?,
+        )?;
+        // renamed so that a crash never leaves half a checkpoint
+        fs::rename(tmp, self.dir.join(CHECKPOINT))?;
+        Ok(())
+    }
+
+    /// Counts of the layers already complete, none if the search starts
+    fn load_checkpoint(&self) -> Result<Vec<u64>, DiskBfsError> {
+        let bytes = match fs::read(self.dir.join(CHECKPOINT)) {
+            Ok(bytes) => bytes,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let checkpoint: Checkpoint = deserialize(&bytes[..]).map_err(DiskBfsError::Checkpoint)?;
+        if checkpoint.n != self.n
+            || checkpoint.tiles != self.tiles
+            || checkpoint.start != self.key(&self.start)
+        {
+            return Err(DiskBfsError::CheckpointMismatch);
+        }
+        Ok(checkpoint.counts)
+    }
+
+    /// Sorted runs of the successors of the layer at `depth`
+    fn write_runs(&self, depth: usize) -> Result<Vec<PathBuf>, DiskBfsError> {
+        let mut runs = Vec::new();
+        let mut buffer = Vec::with_capacity(self.run_len.min(DEFAULT_RUN_LEN) + 4);
+        let mut layer = Keys::open(&self.layer_path(depth))?;
+        loop {
+            let key = layer.next;
+            if let Some(key) = key {
+                self.successors(key, &mut buffer);
+                layer.advance()?;
+            }
+            if buffer.len() >= self.run_len || (key.is_none() && !buffer.is_empty()) {
+                buffer.sort_unstable();
+                buffer.dedup();
+                let run = self.dir.join(format!("run_{}_{}", depth + 1, runs.len()));
+                write_keys(&run, buffer.drain(..))?;
+                runs.push(run);
+            }
+            if key.is_none() {
+                return Ok(runs);
+            }
+        }
+    }
+
+    /// Merge the runs into the layer at `depth`, without the boards of the
+    /// two layers before: in the graph of the moves they are the only ones a
+    /// successor can be in. Returns the number of boards of the layer.
+    fn merge_runs(&self, runs: &[PathBuf], depth: usize) -> Result<u64, DiskBfsError> {
+        let mut readers = runs
+            .iter()
+            .map(|run| Keys::open(run))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut previous = Keys::open(&self.layer_path(depth - 1))?;
+        let mut before = match depth {
+            1 => None,
+            _ => Some(Keys::open(&self.layer_path(depth - 2))?),
+        };
+        let mut heap = BinaryHeap::new();
+        for (i, reader) in readers.iter().enumerate() {
+            if let Some(key) = reader.next {
+                heap.push(Reverse((key, i)));
+            }
+        }
+        let mut writer = BufWriter::new(File::create(self.layer_path(depth))?);
+        let mut count = 0;
+        let mut last = None;
+        while let Some(Reverse((key, i))) = heap.pop() {
+            readers[i].advance()?;
+            if let Some(next) = readers[i].next {
+                heap.push(Reverse((next, i)));
+            }
+            if last == Some(key) {
+                continue;
+            }
+            last = Some(key);
+            let seen = match before {
+                Some(ref mut before) => before.contains(key)?,
+                None => false,
+            };
+            if !seen && !previous.contains(key)? {
+                writer.write_all(&key.to_le_bytes())?;
+                count += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Number of boards at each depth. `on_layer` is called with the depth
+    /// and the count of every layer, including those of a previous run.
+    pub fn run_with<F: FnMut(usize, u64)>(
+        &self,
+        mut on_layer: F,
+    ) -> Result<Vec<u64>, DiskBfsError> {
+        fs::create_dir_all(&self.dir)?;
+        let mut counts = self.load_checkpoint()?;
+        if counts.is_empty() {
+            write_keys(&self.layer_path(0), Some(self.key(&self.start)))?;
+            counts.push(1);
+            self.save_checkpoint(&counts)?;
+        }
+        for (depth, &count) in counts.iter().enumerate() {
+            on_layer(depth, count);
+        }
+        while counts.last() != Some(&0) {
+            let depth = counts.len();
+            if self.max_depth.map_or(false, |max_depth| depth > max_depth) {
+                break;
+            }
+            let runs = self.write_runs(depth - 1)?;
+            let count = self.merge_runs(&runs, depth)?;
+            for run in runs {
+                fs::remove_file(run)?;
+            }
+            counts.push(count);
+            self.save_checkpoint(&counts)?;
+            on_layer(depth, count);
+            if !self.keep_layers && depth >= 2 {
+                fs::remove_file(self.layer_path(depth - 2))?;
+            }
+        }
+        if counts.last() == Some(&0) {
+            Ok(counts[..counts.len() - 1].to_vec())
+        } else {
+            Ok(counts)
+        }
+    }
+
+    pub fn run(&self) -> Result<Vec<u64>, DiskBfsError> {
+        self.run_with(|_, _| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use breadth_first;
+    use heuristic::Zero;
+    use search_problem::TaquinProblem;
+    use std::env;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "npuzzle-disk-bfs-{}-{}",
+            name,
+            ::std::process::id()
+        ))
+    }
+
+    #[test]
+    fn same_as_breadth_first() {
+        let spiral = Taquin::spiral(3);
+        let dir = temp_dir("full");
+        let counts = DiskBfs::new(&dir, &spiral, &[])
+            .unwrap()
+            .run_len(10_000)
+            .run()
+            .unwrap();
+        let distances =
+            breadth_first::distances(&TaquinProblem::new(spiral.clone(), &Zero), &spiral, None);
+        let mut expected = vec![0; distances.values().max().unwrap() + 1];
+        for &d in distances.values() {
+            expected[d] += 1;
+        }
+        assert_eq!(counts, expected);
+        assert_eq!(counts.iter().sum::<u64>(), 181_440);
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn resume() {
+        let spiral = Taquin::spiral(3);
+        let dir = temp_dir("pattern");
+        let bfs = DiskBfs::new(&dir, &spiral, &[1, 2, 3])
+            .unwrap()
+            .run_len(100);
+        let mut layers = Vec::new();
+        let first = bfs
+            .max_depth(5)
+            .run_with(|depth, _| layers.push(depth))
+            .unwrap();
+        assert_eq!(first.len(), 6);
+        assert_eq!(layers, (0..6).collect::<Vec<_>>());
+        let bfs = DiskBfs::new(&dir, &spiral, &[1, 2, 3])
+            .unwrap()
+            .run_len(100);
+        let counts = bfs.run().unwrap();
+        assert_eq!(&counts[..6], &first[..]);
+        // the blank and 3 tiles anywhere on the 9 cells
+        assert_eq!(counts.iter().sum::<u64>(), 9 * 8 * 7 * 6);
+        assert!(DiskBfs::new(&dir, &spiral, &[1, 2]).unwrap().run().is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn bad_pattern() {
+        let spiral = Taquin::spiral(3);
+        assert!(DiskBfs::new("unused", &spiral, &[1, 1]).is_err());
+        assert!(DiskBfs::new("unused", &spiral, &[9]).is_err());
+        assert!(DiskBfs::new("unused", &Taquin::spiral(5), &[]).is_err());
+    }
+}
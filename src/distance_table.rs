@@ -0,0 +1,164 @@
+use bincode::{deserialize, serialize};
+use heuristic::{Heuristic, Manhattan};
+use rank::nb_permutations;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use taquin::Taquin;
+
+/// Biggest dimension whose whole state space fits in a table
+pub const MAX_TABLE_DIM: usize = 3;
+
+const UNREACHABLE: u8 = ::std::u8::MAX;
+
+/// Exact distance to the goal of every board of a small dimension, indexed by
+/// the lehmer rank of the board
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DistanceTable {
+    n: usize,
+    goal: Vec<u64>,
+    distances: Vec<u8>,
+}
+
+impl DistanceTable {
+    /// Breadth first search of the whole state space from `goal`
+    pub fn build(goal: &Taquin) -> Option<Self> {
+        let n = goal.dim();
+        if n > MAX_TABLE_DIM {
+            return None;
+        }
+        // the boards up to `MAX_TABLE_DIM` have a rank
+        let mut distances = vec![UNREACHABLE; nb_permutations(n * n) as usize];
+        let mut open_set = VecDeque::new();
+        let goal_rank = goal.rank().unwrap() as usize;
+        distances[goal_rank] = 0;
+        open_set.push_back(goal_rank);
+        while let Some(r) = open_set.pop_front() {
+            let distance = distances[r];
+            let taquin = Taquin::from_rank(n, r as u64).unwrap();
+            for dir in taquin.neighbours() {
+                let neighbour = taquin.move_piece(dir).unwrap().rank().unwrap() as usize;
+                if distances[neighbour] == UNREACHABLE {
+                    distances[neighbour] = distance + 1;
+                    open_set.push_back(neighbour);
+                }
+            }
+        }
+        Some(DistanceTable {
+            n,
+            goal: goal.iter().cloned().collect(),
+            distances,
+        })
+    }
+
+    pub fn goal(&self) -> Taquin {
+        Taquin::new(self.n, self.goal.clone())
+    }
+
+    /// Whether the table answers for boards toward `goal`
+    pub fn is_for(&self, goal: &Taquin) -> bool {
+        goal.dim() == self.n && goal.iter().eq(self.goal.iter())
+    }
+
+    /// Exact number of moves to the goal, None if unsolvable
+    pub fn distance(&self, taquin: &Taquin) -> Option<u64> {
+        if taquin.dim() != self.n {
+            return None;
+        }
+        match self.distances[taquin.rank()? as usize] {
+            UNREACHABLE => None,
+            d => Some(d as u64),
+        }
+    }
+
+    /// Optimal path from `taquin` to the goal, following decreasing distances
+    pub fn solve(&self, taquin: &Taquin) -> Option<Vec<Taquin>> {
+        let mut distance = self.distance(taquin)?;
+        let mut path = vec![taquin.clone()];
+        while distance != 0 {
+            let next = {
+                let current = path.last().unwrap();
+                current
+                    .neighbours()
+                    .into_iter()
+                    .map(|dir| current.move_piece(dir).unwrap())
+                    .find(|t| self.distance(t) == Some(distance - 1))
+                    .expect("inconsistent distance table")
+            };
+            path.push(next);
+            distance -= 1;
+        }
+        Some(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serialize(self)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let table: DistanceTable = deserialize(&fs::read(path)?[..])?;
+        if table.n > MAX_TABLE_DIM
+            || table.distances.len() != nb_permutations(table.n * table.n) as usize
+        {
+            return Err(From::from("corrupted distance table"));
+        }
+        Ok(table)
+    }
+}
+
+/// Perfect heuristic, falls back to manhattan for the boards the table does
+/// not cover
+impl Heuristic for DistanceTable {
+    fn name(&self) -> &str {
+        "perfect"
+    }
+    fn is_admissible(&self) -> bool {
+        true
+    }
+    fn is_consistent(&self) -> bool {
+        true
+    }
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64 {
+        if self.is_for(goal) {
+            if let Some(d) = self.distance(taquin) {
+                return d;
+            }
+        }
+        Manhattan.evaluate(taquin, goal)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar;
+    use search_problem::TaquinProblem;
+    #[test]
+    fn table() {
+        let spiral = Taquin::spiral(3);
+        let table = DistanceTable::build(&spiral).unwrap();
+        assert_eq!(
+            table.distances.iter().filter(|&&d| d != UNREACHABLE).count(),
+            181440
+        );
+        assert_eq!(table.distance(&spiral), Some(0));
+        let unsolvable = Taquin::new(3, vec![2, 1, 3, 8, 0, 4, 7, 6, 5]);
+        assert_eq!(table.distance(&unsolvable), None);
+
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let path = table.solve(&taquin).unwrap();
+        assert_eq!(path.len() as u64, table.distance(&taquin).unwrap() + 1);
+        assert_eq!(path.last(), Some(&spiral));
+
+        let (astar_path, complexity) =
+            astar::search(&TaquinProblem::new(spiral.clone(), &table), &taquin).unwrap();
+        assert_eq!(astar_path.len(), path.len());
+        assert!(complexity.in_time <= 4 * path.len());
+    }
+    #[test]
+    fn too_big() {
+        assert!(DistanceTable::build(&Taquin::spiral(4)).is_none());
+    }
+}
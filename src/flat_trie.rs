@@ -0,0 +1,235 @@
+//! Pruning automata laid out flat on disk, memory-mapped and queried in place
+//! instead of being deserialised.
+//!
+//! After a 40 bytes header, each node is 4 little endian u32 transitions in
+//! the order of `Dir`: `REDUNDANT`, or the next state with `FAILURE` set for
+//! the failure transitions.
+
+use memmap::Mmap;
+use pruning::{checksum, normalized_dim, AutomatonFileError, PruningAutomaton};
+use std::fs::{self, File};
+use std::path::Path;
+use taquin::Dir;
+use trie::{Automaton, Trie, TrieType};
+
+/// First bytes of every flat automaton file
+pub const FLAT_MAGIC: &[u8; 8] = b"NPZFLATA";
+
+/// Bumped whenever the header or the layout of the nodes changes
+pub const FLAT_FORMAT_VERSION: u32 = 2;
+
+// magic, version, dim, depth, nb_nodes, padding, checksum
+const HEADER_LEN: usize = 40;
+
+const REDUNDANT: u32 = ::std::u32::MAX;
+const FAILURE: u32 = 1 << 31;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buf)
+}
+
+/// Memory-mapped pruning automaton
+pub struct FlatTrie {
+    pub dim: usize,
+    pub depth: usize,
+    nb_nodes: usize,
+    map: Mmap,
+}
+
+impl FlatTrie {
+    /// Write `automaton` in the flat layout, refusing the automata with a
+    /// transition out of them so that the files written are valid
+    pub fn write<P: AsRef<Path>>(
+        automaton: &PruningAutomaton,
+        path: P,
+    ) -> Result<(), AutomatonFileError> {
+        let nodes = &automaton.trie.0;
+        if nodes.is_empty() || nodes.len() >= FAILURE as usize {
+            return Err(AutomatonFileError::Corrupted);
+        }
+        let mut bytes = Vec::with_capacity(HEADER_LEN + nodes.len() * 16);
+        bytes.extend_from_slice(FLAT_MAGIC);
+        bytes.extend_from_slice(&FLAT_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(automaton.dim as u32).to_le_bytes());
+        bytes.extend_from_slice(&(automaton.depth as u32).to_le_bytes());
+        bytes.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+        bytes.resize(HEADER_LEN, 0);
+        for (i, node) in nodes.iter().enumerate() {
+            for &dir in [Dir::Right, Dir::Up, Dir::Down, Dir::Left].iter() {
+                let t = match node[dir] {
+                    TrieType::Failure(s) | TrieType::Match(s) if s >= nodes.len() => {
+                        return Err(AutomatonFileError::BadTransition(i));
+                    }
+                    TrieType::Redundant => REDUNDANT,
+                    TrieType::Failure(s) => s as u32 | FAILURE,
+                    TrieType::Match(s) => s as u32,
+                };
+                bytes.extend_from_slice(&t.to_le_bytes());
+            }
+        }
+        let sum = checksum(&bytes[HEADER_LEN..]);
+        bytes[32..HEADER_LEN].copy_from_slice(&sum.to_le_bytes());
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Map the file at `path`, only the header and the size are checked so
+    /// that opening is instant: `verify` checks the whole file. The file must
+    /// not be modified while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AutomatonFileError> {
+        let file = File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < FLAT_MAGIC.len() || &map[..FLAT_MAGIC.len()] != FLAT_MAGIC {
+            return Err(AutomatonFileError::BadMagic);
+        }
+        if map.len() < HEADER_LEN {
+            return Err(AutomatonFileError::Truncated);
+        }
+        let version = read_u32(&map, 8);
+        if version != FLAT_FORMAT_VERSION {
+            return Err(AutomatonFileError::UnsupportedVersion(version));
+        }
+        let dim = read_u32(&map, 12) as usize;
+        let depth = read_u32(&map, 16) as usize;
+        let nb_nodes = read_u64(&map, 20) as usize;
+        // the searches start on the first node
+        if nb_nodes == 0 {
+            return Err(AutomatonFileError::Corrupted);
+        }
+        let len = nb_nodes
+            .checked_mul(16)
+            .and_then(|body| body.checked_add(HEADER_LEN));
+        if len != Some(map.len()) {
+            return Err(AutomatonFileError::Truncated);
+        }
+        Ok(FlatTrie {
+            dim,
+            depth,
+            nb_nodes,
+            map,
+        })
+    }
+
+    pub fn nb_nodes(&self) -> usize {
+        self.nb_nodes
+    }
+
+    /// Check the checksum and that no transition leaves the automaton, a
+    /// corrupted file could make the searches panic
+    pub fn verify(&self) -> Result<(), AutomatonFileError> {
+        let body = &self.map[HEADER_LEN..];
+        if checksum(body) != read_u64(&self.map, 32) {
+            return Err(AutomatonFileError::BadChecksum);
+        }
+        for (i, chunk) in body.chunks(4).enumerate() {
+            let t = read_u32(chunk, 0);
+            if t != REDUNDANT && (t & !FAILURE) as usize >= self.nb_nodes {
+                return Err(AutomatonFileError::BadTransition(i / 4));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the whole automaton back into memory
+    pub fn to_automaton(&self) -> PruningAutomaton {
+        let mut trie = Trie::new();
+        let root = trie.0[0];
+        trie.0.resize(self.nb_nodes, root);
+        for (state, node) in trie.0.iter_mut().enumerate() {
+            for &dir in [Dir::Right, Dir::Up, Dir::Down, Dir::Left].iter() {
+                node[dir] = self.transition(state, dir);
+            }
+        }
+        PruningAutomaton {
+            dim: self.dim,
+            depth: self.depth,
+            trie,
+        }
+    }
+
+    /// Whether the automaton prunes correctly the boards of dimension `dim`
+    pub fn is_for(&self, dim: usize) -> bool {
+        normalized_dim(dim, self.depth) == normalized_dim(self.dim, self.depth)
+    }
+
+    fn transition(&self, state: usize, dir: Dir) -> TrieType {
+        match read_u32(&self.map, HEADER_LEN + (state * 4 + dir as usize) * 4) {
+            REDUNDANT => TrieType::Redundant,
+            t if t & FAILURE != 0 => TrieType::Failure((t & !FAILURE) as usize),
+            t => TrieType::Match(t as usize),
+        }
+    }
+}
+
+impl Automaton for FlatTrie {
+    fn change_true_state(&self, old_state: &TrieType, dir: Dir) -> TrieType {
+        match *old_state {
+            TrieType::Redundant => TrieType::Redundant,
+            TrieType::Failure(s) | TrieType::Match(s) => self.transition(s, dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    #[test]
+    fn flat() {
+        let automaton = PruningAutomaton::load("prunning_automaton_3x3_d10.serde").unwrap();
+        let path = env::temp_dir().join(format!("npuzzle-flat-{}", ::std::process::id()));
+        FlatTrie::write(&automaton, &path).unwrap();
+        let flat = FlatTrie::open(&path).unwrap();
+        flat.verify().unwrap();
+        assert_eq!(flat.nb_nodes(), automaton.trie.0.len());
+        assert!(flat.is_for(3) && !flat.is_for(4));
+        assert_eq!(flat.to_automaton().trie, automaton.trie);
+        let dirs = [Dir::Right, Dir::Up, Dir::Down, Dir::Left];
+        for state in 0..automaton.trie.0.len() {
+            for &d in dirs.iter() {
+                let s = TrieType::Failure(state);
+                assert_eq!(
+                    flat.change_true_state(&s, d),
+                    automaton.trie.change_true_state(&s, d)
+                );
+            }
+        }
+
+        // a transition out of the automaton, with or without its checksum
+        let mut bytes = fs::read(&path).unwrap();
+        let t = HEADER_LEN + 5 * 16 + 4;
+        bytes[t..t + 4].copy_from_slice(&(flat.nb_nodes() as u32).to_le_bytes());
+        drop(flat);
+        fs::write(&path, &bytes).unwrap();
+        match FlatTrie::open(&path).unwrap().verify() {
+            Err(AutomatonFileError::BadChecksum) => (),
+            _ => panic!("verified a flat automaton with a bad checksum"),
+        }
+        let sum = checksum(&bytes[HEADER_LEN..]);
+        bytes[32..HEADER_LEN].copy_from_slice(&sum.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        match FlatTrie::open(&path).unwrap().verify() {
+            Err(AutomatonFileError::BadTransition(5)) => (),
+            _ => panic!("verified a flat automaton with a transition out of it"),
+        }
+        // node counts of an empty automaton, or overflowing the size
+        for &nb_nodes in [0, ::std::u64::MAX / 8].iter() {
+            bytes[20..28].copy_from_slice(&nb_nodes.to_le_bytes());
+            fs::write(&path, &bytes).unwrap();
+            assert!(FlatTrie::open(&path).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+        match FlatTrie::open("prunning_automaton_3x3_d10.serde") {
+            Err(AutomatonFileError::BadMagic) => (),
+            _ => panic!("opened a serde automaton as a flat one"),
+        }
+    }
+}
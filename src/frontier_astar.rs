@@ -0,0 +1,346 @@
+use complexity::Complexity;
+use num_traits::Zero;
+use search_problem::SearchProblem;
+use std::cmp::{Ord, Ordering, PartialOrd};
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::ops::Sub;
+use zobrist::BuildZobristHasher;
+
+/// Most actions from a state, they are flagged in `Node::used`
+const MAX_ACTIONS: usize = 32;
+
+/// Open state of the frontier
+struct Node<C> {
+    g_cost: C,
+    depth: usize,
+    /// Bit `i` is set once the `i`th successor is known to be open or closed
+    used: u32,
+    /// Index in the relays of the ancestor where the path is split
+    relay: Option<usize>,
+}
+
+/// Outdated once its state is expanded or reached cheaper
+struct Entry<S, C> {
+    f_cost: C,
+    g_cost: C,
+    state: S,
+}
+
+impl<S, C: Ord> PartialEq for Entry<S, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S, C: Ord> Eq for Entry<S, C> {}
+
+impl<S, C: Ord> PartialOrd for Entry<S, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S, C: Ord> Ord for Entry<S, C> {
+    /// Greater is expanded first: lowest f-cost, then farthest from the start
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .f_cost
+            .cmp(&self.f_cost)
+            .then(self.g_cost.cmp(&other.g_cost))
+    }
+}
+
+/// Where a path is split
+#[derive(Copy, Clone)]
+enum Split {
+    /// First state at least as far from the start as from the target
+    Halfway,
+    /// State at this depth
+    Depth(usize),
+}
+
+/// Target reached by a frontier search, with the state where its path is
+/// split, its cost and its depth
+struct Found<S, C> {
+    state: S,
+    g_cost: C,
+    depth: usize,
+    relay: Option<(S, C, usize)>,
+}
+
+/// Astar from `start` to `target`, or to a goal, keeping only the open
+/// states. `rest` is the cost from `target` to the goal, it turns the
+/// heuristic of the problem into a lower bound of the cost to `target`.
+/// None as well when an action can't be undone or a state has more than
+/// `MAX_ACTIONS` actions.
+fn frontier<P>(
+    problem: &P,
+    start: &P::State,
+    target: Option<&P::State>,
+    rest: P::Cost,
+    split: Split,
+    complexity: &mut Complexity,
+) -> Option<Found<P::State, P::Cost>>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+    P::Cost: Sub<Output = P::Cost>,
+{
+    let heuristic = |state: &P::State| problem.heuristic(state).max(rest) - rest;
+    let mut open: HashMap<P::State, Node<P::Cost>, BuildZobristHasher> = HashMap::default();
+    let mut relays = Vec::new();
+    let mut open_set = BinaryHeap::new();
+    open.insert(
+        start.clone(),
+        Node {
+            g_cost: P::Cost::zero(),
+            depth: 0,
+            used: 0,
+            relay: None,
+        },
+    );
+    open_set.push(Entry {
+        f_cost: heuristic(start),
+        g_cost: P::Cost::zero(),
+        state: start.clone(),
+    });
+    while let Some(Entry {
+        f_cost,
+        g_cost,
+        state,
+    }) = open_set.pop()
+    {
+        match open.get(&state) {
+            Some(node) if node.g_cost == g_cost => (),
+            _ => continue,
+        }
+        // closed states are forgotten
+        let node = open.remove(&state).unwrap();
+        let is_target = match target {
+            Some(target) => state == *target,
+            None => problem.is_goal(&state),
+        };
+        if is_target {
+            return Some(Found {
+                relay: node.relay.map(|i| relays.swap_remove(i)),
+                state,
+                g_cost,
+                depth: node.depth,
+            });
+        }
+        if problem.interrupted() {
+            return None;
+        }
+        // the searches of the halves have bounds of their own
+        if target.is_none() {
+            problem.progress(Some(f_cost), open.len());
+        }
+        complexity.in_time += 1;
+        let successors = problem.successors(&state);
+        if successors.len() > MAX_ACTIONS {
+            return None;
+        }
+        for (slot, (action, cost)) in successors.into_iter().enumerate() {
+            if node.used & 1 << slot != 0 {
+                continue;
+            }
+            let next = problem.apply(&state, action);
+            // the action leading back is used: `state` is closed
+            let back = problem
+                .successors(&next)
+                .into_iter()
+                .take(MAX_ACTIONS)
+                .position(|(action, _)| problem.apply(&next, action) == state)?;
+            let g = g_cost + cost;
+            let depth = node.depth + 1;
+            let used = match open.get_mut(&next) {
+                Some(known) => {
+                    known.used |= 1 << back;
+                    if known.g_cost <= g {
+                        continue;
+                    }
+                    known.used
+                }
+                None => 1 << back,
+            };
+            let h = heuristic(&next);
+            let relay = node.relay.or_else(|| {
+                let is_relay = match split {
+                    Split::Halfway => g >= h,
+                    Split::Depth(d) => depth == d,
+                };
+                if is_relay {
+                    relays.push((next.clone(), g, depth));
+                    Some(relays.len() - 1)
+                } else {
+                    None
+                }
+            });
+            open.insert(
+                next.clone(),
+                Node {
+                    g_cost: g,
+                    depth,
+                    used,
+                    relay,
+                },
+            );
+            open_set.push(Entry {
+                f_cost: g + h,
+                g_cost: g,
+                state: next,
+            });
+        }
+        complexity.in_size = complexity.in_size.max(open.len() + relays.len());
+    }
+    None
+}
+
+/// Cheapest path from `start` to `target`, split at a relay whose halves are
+/// found again by divide and conquer
+fn path<P>(
+    problem: &P,
+    start: &P::State,
+    target: Option<&P::State>,
+    rest: P::Cost,
+    complexity: &mut Complexity,
+) -> Option<Vec<P::State>>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+    P::Cost: Sub<Output = P::Cost>,
+{
+    let mut found = frontier(problem, start, target, rest, Split::Halfway, complexity)?;
+    loop {
+        match found.depth {
+            0 => return Some(vec![found.state]),
+            1 => return Some(vec![found.state, start.clone()]),
+            _ => (),
+        }
+        match found.relay {
+            Some((_, _, depth)) if depth < found.depth => break,
+            // with actions of different costs the halfway can be the target
+            _ => {
+                let depth = found.depth / 2;
+                found = frontier(
+                    problem,
+                    start,
+                    target,
+                    rest,
+                    Split::Depth(depth),
+                    complexity,
+                )?;
+            }
+        }
+    }
+    let (relay, relay_cost, _) = found.relay.unwrap();
+    // the relay is on a cheapest path, so are both halves
+    let mut path_to_target = path(problem, &relay, Some(&found.state), rest, complexity)?;
+    let to_relay = found.g_cost - relay_cost + rest;
+    let path_to_relay = path(problem, start, Some(&relay), to_relay, complexity)?;
+    path_to_target.extend(path_to_relay.into_iter().skip(1));
+    Some(path_to_target)
+}
+
+/// Frontier search version of astar: only the open states are kept, each
+/// with the actions leading to states already open or closed, so that closed
+/// states are never generated again. Memory is proportional to the frontier.
+/// The path is not kept either: every state remembers the ancestor halfway to
+/// the goal, and both halves are searched again, down to single moves.
+///
+/// Actions must be reversible and at most `MAX_ACTIONS` per state, None is
+/// returned otherwise. The heuristic must be consistent for the path to be
+/// optimal. The pruning of the problem is ignored: every successor is
+/// generated to flag the way back to its parent, a pruned one could reach its
+/// closed parent again.
+pub fn search<P>(problem: &P, start: &P::State) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq,
+    P::Cost: Sub<Output = P::Cost>,
+{
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 0,
+    };
+    let path = path(problem, start, None, P::Cost::zero(), &mut complexity)?;
+    Some((path, complexity))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar;
+    use dijkstra;
+    use heuristic::Manhattan;
+    use search_problem::test::assert_optimal_like_astar;
+    use search_problem::{never_redundant, no_pruning, Closures, TaquinProblem};
+    use taquin::Taquin;
+
+    #[test]
+    fn optimal() {
+        assert_optimal_like_astar(|problem, taquin| search(problem, taquin).map(|(path, _)| path));
+    }
+    #[test]
+    fn weighted() {
+        // from 0 to 20 by steps of 1 costing 1 or steps of 3 costing 2
+        let problem = Closures::new(
+            |_: &i64| vec![(1i64, 1u64), (-1, 1), (3, 2), (-3, 2)],
+            |&n: &i64, step| n + step,
+            |_: &i64| 0u64,
+            |&n: &i64| n == 20,
+            (),
+            no_pruning,
+            never_redundant,
+        );
+        let (path, _) = search(&problem, &0).unwrap();
+        let (cheapest, _) = dijkstra::search(&problem, &0).unwrap();
+        let cost = |path: &[i64]| {
+            path.windows(2)
+                .map(|w| if (w[0] - w[1]).abs() == 3 { 2 } else { 1 })
+                .sum::<u64>()
+        };
+        assert_eq!(cost(&path), cost(&cheapest));
+        assert_eq!((path.first(), path.last()), (Some(&20), Some(&0)));
+    }
+    #[test]
+    fn unsupported() {
+        let one_way = Closures::new(
+            |_: &i64| vec![(1i64, 1u64)],
+            |&n: &i64, step| n + step,
+            |_: &i64| 0u64,
+            |&n: &i64| n == 5,
+            (),
+            no_pruning,
+            never_redundant,
+        );
+        assert!(search(&one_way, &0).is_none());
+        let too_many = Closures::new(
+            |_: &i64| (-20i64..=20).map(|step| (step, 1u64)).collect::<Vec<_>>(),
+            |&n: &i64, step| n + step,
+            |_: &i64| 0u64,
+            |&n: &i64| n == 100,
+            (),
+            no_pruning,
+            never_redundant,
+        );
+        assert!(search(&too_many, &0).is_none());
+    }
+    #[test]
+    fn smaller_than_astar() {
+        let spiral = Taquin::spiral(4);
+        // 28 moves away
+        let taquin = (0..60).fold(spiral.clone(), |t, i| {
+            let dirs = t.neighbours();
+            t.move_piece(dirs[(4 * i * i + i / 2) % dirs.len()])
+                .unwrap()
+        });
+        let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+        let (path, complexity) = search(&problem, &taquin).unwrap();
+        let (optimal, astar_complexity) = astar::search(&problem, &taquin).unwrap();
+        assert_eq!(path.len(), 29);
+        assert_eq!(path.len(), optimal.len());
+        assert!(complexity.in_size < astar_complexity.in_size);
+    }
+}
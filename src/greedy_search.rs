@@ -1,19 +1,82 @@
-use num_traits::Zero;
-use std::cmp::{Ord};
-use std::collections::HashSet;
-use std::fmt::{Debug, Display};
-use std::hash::{Hash};
 use complexity::Complexity;
+use num_traits::Zero;
+use search_problem::{never_redundant, no_pruning, Closures, SearchProblem};
+use std::cmp::{Ord, Ordering, PartialOrd};
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use zobrist::BuildZobristHasher;
+
+/// Order in which the states of equal heuristic are expanded
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum TieBreak {
+    /// Last generated first, dives like a depth first search
+    #[default]
+    Newest,
+    /// First generated first
+    Oldest,
+    /// Closest to the start first, for shorter paths
+    LowestCost,
+    /// Farthest from the start first
+    HighestCost,
+}
+
+impl TieBreak {
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "newest" => Some(TieBreak::Newest),
+            "oldest" => Some(TieBreak::Oldest),
+            "lowest_cost" => Some(TieBreak::LowestCost),
+            "highest_cost" => Some(TieBreak::HighestCost),
+            _ => None,
+        }
+    }
+}
+
+/// Open state, `index` in the nodes and in the order of generation
+struct Entry<C> {
+    hcost: C,
+    gcost: C,
+    index: usize,
+    tie_break: TieBreak,
+}
+
+impl<C: Ord> PartialEq for Entry<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<C: Ord> Eq for Entry<C> {}
+
+impl<C: Ord> PartialOrd for Entry<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Ord> Ord for Entry<C> {
+    /// Greater is expanded first
+    fn cmp(&self, other: &Self) -> Ordering {
+        let newest = self.index.cmp(&other.index);
+        other.hcost.cmp(&self.hcost).then(match self.tie_break {
+            TieBreak::Newest => newest,
+            TieBreak::Oldest => newest.reverse(),
+            TieBreak::LowestCost => other.gcost.cmp(&self.gcost).then(newest),
+            TieBreak::HighestCost => self.gcost.cmp(&other.gcost).then(newest),
+        })
+    }
+}
 
 pub fn greedy_search<N, C, FN, IN, FH, FS, FA, A>(
     start: &N,
     neighbours_actions: FN,
     perform_action: FA,
-    _heuristic: FH,
+    heuristic: FH,
     success: FS,
 ) -> Option<(Vec<N>, Complexity)>
 where
-    N: Clone + Hash + Eq + Debug + Display,
+    N: Clone + Hash + Eq + Debug,
     C: Zero + Ord + Copy + Debug,
     FN: Fn(&N) -> IN,
     IN: IntoIterator<Item = (A, C)>,
@@ -22,33 +85,150 @@ where
     FA: Fn(&N, A) -> N,
     A: Copy,
 {
-    const DEFAULT_CLOSED_SET_SIZE: usize = 0x1_0000;
-	
-    let mut complexity = Complexity { in_time : 0, in_size : 0};
-    let mut closed_set = HashSet::with_capacity(DEFAULT_CLOSED_SET_SIZE);
-
-	let mut current_state = start.clone();
-	let mut paths = Vec::new();
-	'blocked: while !success(&current_state) {
-		complexity.in_time += 1;
-		for (action, _) in neighbours_actions(&current_state) {
-			let next_state = perform_action(&current_state, action);
-			if closed_set.insert(next_state.clone()) == false {
-				continue
-			}
-			paths.push(current_state);
-			current_state = next_state;
-			continue 'blocked
-		}
-		current_state = match paths.pop() {
-			Some(state) => state,
-			None => {
-				eprintln!("YOU LIED TO ME, IT'S NOT SOLVABLE, YOU KNOW IT, I WILL FIND AND I WILL KILL YOU");
-				::std::process::exit(1);
-			}
-		}
-	}
-	complexity.in_size = closed_set.len();
-	paths.reverse();
-    Some((paths, complexity))
+    search(
+        &Closures::new(
+            neighbours_actions,
+            perform_action,
+            heuristic,
+            success,
+            (),
+            no_pruning,
+            never_redundant,
+        ),
+        start,
+    )
+}
+
+/// Greedy best first search with the default tie break and no node budget
+pub fn search<P>(problem: &P, start: &P::State) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq + Debug,
+{
+    search_with(problem, start, TieBreak::default(), None)
+}
+
+/// Greedy best first search: the open state of lowest heuristic is always
+/// expanded first, whatever its cost from the start. Each state is generated
+/// at most once. Gives up after expanding `max_nodes` states.
+pub fn search_with<P>(
+    problem: &P,
+    start: &P::State,
+    tie_break: TieBreak,
+    max_nodes: Option<usize>,
+) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+    P::State: Hash + Eq + Debug,
+{
+    const DEFAULT_SEEN_SET_SIZE: usize = 0x1_0000;
+
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 0,
+    };
+    let mut seen =
+        HashSet::with_capacity_and_hasher(DEFAULT_SEEN_SET_SIZE, BuildZobristHasher::default());
+    // every generated state with the index of its predecessor
+    let mut nodes: Vec<(P::State, Option<usize>)> = vec![(start.clone(), None)];
+    let mut open_set = BinaryHeap::new();
+    seen.insert(start.clone());
+    open_set.push(Entry {
+        hcost: problem.heuristic(start),
+        gcost: P::Cost::zero(),
+        index: 0,
+        tie_break,
+    });
+
+    while let Some(current) = open_set.pop() {
+        if problem.is_goal(&nodes[current.index].0) {
+            complexity.in_size = nodes.len();
+            let mut path = Vec::new();
+            let mut index = Some(current.index);
+            while let Some(i) = index {
+                path.push(nodes[i].0.clone());
+                index = nodes[i].1;
+            }
+            return Some((path, complexity));
+        }
+        if problem.interrupted() || max_nodes.map_or(false, |max| complexity.in_time >= max) {
+            return None;
+        }
+        problem.progress(None, open_set.len());
+        complexity.in_time += 1;
+
+        for (action, cost) in problem.successors(&nodes[current.index].0) {
+            let state = problem.apply(&nodes[current.index].0, action);
+            if !seen.insert(state.clone()) {
+                continue;
+            }
+            open_set.push(Entry {
+                hcost: problem.heuristic(&state),
+                gcost: current.gcost + cost,
+                index: nodes.len(),
+                tie_break,
+            });
+            nodes.push((state, Some(current.index)));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar;
+    use heuristic::{Manhattan, Zero};
+    use search_problem::TaquinProblem;
+    use taquin::Taquin;
+
+    fn is_path(path: &[Taquin]) -> bool {
+        path.windows(2).all(|w| w[0].dir_to(&w[1]).is_some())
+    }
+
+    #[test]
+    fn tie_breaks() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+        for &tie_break in [
+            TieBreak::Newest,
+            TieBreak::Oldest,
+            TieBreak::LowestCost,
+            TieBreak::HighestCost,
+        ]
+        .iter()
+        {
+            let (path, complexity) = search_with(&problem, &taquin, tie_break, None).unwrap();
+            assert_eq!(path.first(), Some(&spiral));
+            assert_eq!(path.last(), Some(&taquin));
+            assert!(is_path(&path));
+            assert!(complexity.in_size <= 4 * complexity.in_time + 1);
+        }
+    }
+    #[test]
+    fn breadth_first() {
+        // without heuristic, oldest first is a breadth first search
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let problem = TaquinProblem::new(spiral.clone(), &Zero);
+        let (path, _) = search_with(&problem, &taquin, TieBreak::Oldest, None).unwrap();
+        let (optimal, _) =
+            astar::search(&TaquinProblem::new(spiral, &Manhattan), &taquin).unwrap();
+        assert_eq!(path.len(), optimal.len());
+    }
+    #[test]
+    fn node_budget() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let problem = TaquinProblem::new(spiral, &Zero);
+        assert!(search_with(&problem, &taquin, TieBreak::Oldest, Some(10)).is_none());
+    }
+    #[test]
+    fn unsolvable() {
+        // the 2x2 state space is small enough to be exhausted
+        let taquin = Taquin::new(2, vec![2, 1, 3, 0]);
+        let problem = TaquinProblem::new(Taquin::spiral(2), &Manhattan);
+        assert!(search(&problem, &taquin).is_none());
+    }
 }
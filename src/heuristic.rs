@@ -0,0 +1,189 @@
+use taquin::Taquin;
+
+/// Estimation of the number of moves left to reach the goal
+pub trait Heuristic: Sync {
+    fn name(&self) -> &str;
+
+    /// Never overestimates the number of moves left
+    fn is_admissible(&self) -> bool;
+
+    /// Never decreases by more than one on a move
+    fn is_consistent(&self) -> bool;
+
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64;
+}
+
+pub struct Manhattan;
+
+impl Heuristic for Manhattan {
+    fn name(&self) -> &str {
+        "manhattan"
+    }
+    fn is_admissible(&self) -> bool {
+        true
+    }
+    fn is_consistent(&self) -> bool {
+        true
+    }
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64 {
+        taquin.manhattan_heuristic(goal)
+    }
+}
+
+/// Overestimates on some boards, see the check_heuristic binary
+pub struct LinearConflict;
+
+impl Heuristic for LinearConflict {
+    fn name(&self) -> &str {
+        "linear_conflict"
+    }
+    fn is_admissible(&self) -> bool {
+        false
+    }
+    fn is_consistent(&self) -> bool {
+        false
+    }
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64 {
+        taquin.manhattan_heuristic_linear_conflict(goal)
+    }
+}
+
+pub struct HammingDistance;
+
+impl Heuristic for HammingDistance {
+    fn name(&self) -> &str {
+        "hamming_distance"
+    }
+    fn is_admissible(&self) -> bool {
+        true
+    }
+    fn is_consistent(&self) -> bool {
+        true
+    }
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64 {
+        taquin.hamming_distance_heuristic(goal)
+    }
+}
+
+/// Always 0, turns astar into a uniform cost search
+pub struct Zero;
+
+impl Heuristic for Zero {
+    fn name(&self) -> &str {
+        "zero"
+    }
+    fn is_admissible(&self) -> bool {
+        true
+    }
+    fn is_consistent(&self) -> bool {
+        true
+    }
+    fn evaluate(&self, _taquin: &Taquin, _goal: &Taquin) -> u64 {
+        0
+    }
+}
+
+/// Maximum of several heuristics, admissible (resp. consistent) if all of
+/// them are
+pub struct Max {
+    name: String,
+    heuristics: Vec<Box<dyn Heuristic>>,
+}
+
+impl Max {
+    pub fn new(heuristics: Vec<Box<dyn Heuristic>>) -> Self {
+        let names: Vec<&str> = heuristics.iter().map(|h| h.name()).collect();
+        Max {
+            name: format!("max({})", names.join(",")),
+            heuristics,
+        }
+    }
+}
+
+impl Heuristic for Max {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn is_admissible(&self) -> bool {
+        self.heuristics.iter().all(|h| h.is_admissible())
+    }
+    fn is_consistent(&self) -> bool {
+        self.heuristics.iter().all(|h| h.is_consistent())
+    }
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64 {
+        self.heuristics
+            .iter()
+            .map(|h| h.evaluate(taquin, goal))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Plain functions can be used as heuristics, nothing is known about them
+impl Heuristic for fn(&Taquin, &Taquin) -> u64 {
+    fn name(&self) -> &str {
+        "custom"
+    }
+    fn is_admissible(&self) -> bool {
+        false
+    }
+    fn is_consistent(&self) -> bool {
+        false
+    }
+    fn evaluate(&self, taquin: &Taquin, goal: &Taquin) -> u64 {
+        self(taquin, goal)
+    }
+}
+
+/// Every built-in heuristic
+pub fn builtins() -> Vec<Box<dyn Heuristic>> {
+    vec![
+        Box::new(Manhattan),
+        Box::new(LinearConflict),
+        Box::new(HammingDistance),
+        Box::new(Zero),
+    ]
+}
+
+/// Look up a built-in heuristic, `max(a,b,...)` combines admissible ones
+pub fn by_name(name: &str) -> Option<Box<dyn Heuristic>> {
+    let name = name.trim();
+    if name.starts_with("max(") && name.ends_with(')') {
+        let heuristics = name[4..name.len() - 1]
+            .split(',')
+            .map(by_name)
+            .collect::<Option<Vec<_>>>()?;
+        if heuristics.is_empty() || !heuristics.iter().all(|h| h.is_admissible()) {
+            return None;
+        }
+        return Some(Box::new(Max::new(heuristics)));
+    }
+    builtins().into_iter().find(|h| h.name() == name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn registry() {
+        for h in builtins() {
+            assert_eq!(by_name(h.name()).unwrap().name(), h.name());
+        }
+        assert!(by_name("euclidean").is_none());
+    }
+    #[test]
+    fn max() {
+        let h = by_name("max(manhattan, hamming_distance)").unwrap();
+        assert_eq!(h.name(), "max(manhattan,hamming_distance)");
+        assert!(h.is_admissible());
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let spiral = Taquin::spiral(3);
+        assert_eq!(
+            h.evaluate(&taquin, &spiral),
+            ::std::cmp::max(
+                taquin.manhattan_heuristic(&spiral),
+                taquin.hamming_distance_heuristic(&spiral)
+            )
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use breadth_first;
+use heuristic::{Heuristic, Zero};
+use search_problem::TaquinProblem;
+use std::collections::HashMap;
+use std::fmt;
+use taquin::Taquin;
+use zobrist::BuildZobristHasher;
+
+/// Biggest dimension whose state space can be enumerated
+pub const MAX_CHECK_DIM: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    /// The heuristic is greater than the real distance to the goal
+    Overestimate {
+        taquin: Taquin,
+        heuristic: u64,
+        distance: u64,
+    },
+    /// The heuristic drops by more than one between two neighbours
+    Inconsistent {
+        taquin: Taquin,
+        heuristic: u64,
+        neighbour: Taquin,
+        neighbour_heuristic: u64,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::Overestimate {
+                ref taquin,
+                heuristic,
+                distance,
+            } => write!(
+                f,
+                "overestimate: h = {} but distance = {}{}",
+                heuristic, distance, taquin
+            ),
+            Violation::Inconsistent {
+                ref taquin,
+                heuristic,
+                ref neighbour,
+                neighbour_heuristic,
+            } => write!(
+                f,
+                "inconsistent: h = {}{}has neighbour with h = {}{}",
+                heuristic, taquin, neighbour_heuristic, neighbour
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckReport {
+    /// Number of states reachable from the goal
+    pub nb_states: usize,
+    pub nb_overestimates: usize,
+    pub nb_inconsistencies: usize,
+    /// First violations found
+    pub counterexamples: Vec<Violation>,
+}
+
+impl CheckReport {
+    pub fn is_admissible(&self) -> bool {
+        self.nb_overestimates == 0
+    }
+    pub fn is_consistent(&self) -> bool {
+        self.nb_inconsistencies == 0
+    }
+}
+
+/// Exact distance to `goal` of every reachable state, by a breadth first
+/// search from the goal (moves are reversible)
+pub fn goal_distances(goal: &Taquin) -> HashMap<Taquin, usize, BuildZobristHasher> {
+    breadth_first::distances(&TaquinProblem::new(goal.clone(), &Zero), goal, None)
+}
+
+/// Check `heuristic` on every state reachable from `goal`, keeping at most
+/// `max_counterexamples` violations. Only small boards can be enumerated.
+pub fn check(
+    heuristic: &dyn Heuristic,
+    goal: &Taquin,
+    max_counterexamples: usize,
+) -> Option<CheckReport> {
+    if goal.dim() > MAX_CHECK_DIM {
+        return None;
+    }
+    let distances = goal_distances(goal);
+    let mut report = CheckReport {
+        nb_states: distances.len(),
+        nb_overestimates: 0,
+        nb_inconsistencies: 0,
+        counterexamples: Vec::new(),
+    };
+    for (taquin, &distance) in distances.iter() {
+        let distance = distance as u64;
+        let h = heuristic.evaluate(taquin, goal);
+        if h > distance {
+            report.nb_overestimates += 1;
+            if report.counterexamples.len() < max_counterexamples {
+                report.counterexamples.push(Violation::Overestimate {
+                    taquin: taquin.clone(),
+                    heuristic: h,
+                    distance,
+                });
+            }
+        }
+        // every edge is seen from both ends, only report it from the higher one
+        for dir in taquin.neighbours() {
+            let neighbour = taquin.move_piece(dir).unwrap();
+            let neighbour_h = heuristic.evaluate(&neighbour, goal);
+            if h > neighbour_h + 1 {
+                report.nb_inconsistencies += 1;
+                if report.counterexamples.len() < max_counterexamples {
+                    report.counterexamples.push(Violation::Inconsistent {
+                        taquin: taquin.clone(),
+                        heuristic: h,
+                        neighbour,
+                        neighbour_heuristic: neighbour_h,
+                    });
+                }
+            }
+        }
+    }
+    Some(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use heuristic::{HammingDistance, LinearConflict, Manhattan};
+    #[test]
+    fn distances() {
+        let distances = goal_distances(&Taquin::spiral(3));
+        assert_eq!(distances.len(), 181440);
+        assert_eq!(distances.values().max(), Some(&30));
+    }
+    #[test]
+    fn builtins() {
+        let spiral = Taquin::spiral(3);
+        for h in [&Manhattan as &dyn Heuristic, &HammingDistance].iter() {
+            let report = check(*h, &spiral, 1).unwrap();
+            assert!(report.is_admissible());
+            assert!(report.is_consistent());
+        }
+    }
+    #[test]
+    fn linear_conflict() {
+        let report = check(&LinearConflict, &Taquin::spiral(3), 1).unwrap();
+        assert_eq!(report.is_admissible(), LinearConflict.is_admissible());
+        assert_eq!(report.is_consistent(), LinearConflict.is_consistent());
+    }
+    #[test]
+    fn overestimate() {
+        let double: fn(&Taquin, &Taquin) -> u64 = |t, s| 2 * t.manhattan_heuristic(s);
+        let report = check(&double, &Taquin::spiral(3), 1).unwrap();
+        assert!(!report.is_admissible());
+        assert!(!report.is_consistent());
+        assert_eq!(report.counterexamples.len(), 1);
+    }
+    #[test]
+    fn too_big() {
+        assert!(check(&Manhattan, &Taquin::spiral(4), 1).is_none());
+    }
+}
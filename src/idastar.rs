@@ -1,10 +1,23 @@
+use bincode::{deserialize, serialize};
 use complexity::Complexity;
 use num_traits::Zero;
+use search_problem::{Closures, SearchProblem};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
 use std::fmt::Debug;
+use std::fs;
+use std::hash::{BuildHasher, Hash};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
+use std::time::{Duration, Instant};
+use zobrist::BuildZobristHasher;
 
 enum Res<C> {
     Found,
     MinFCost(C),
+    Interrupted,
 }
 
 use self::Res::*;
@@ -32,105 +45,649 @@ where
     FA: Fn(&N, A) -> N,
     A: Copy,
 {
-    fn aux<N, C, FN, IN, FH, FS, S, CS, IR, FA, A>(
-        start: N,
-        neighbours_actions: &FN,
-        perform_action: &FA,
-        heuristic: &FH,
-        success: &FS,
-        path: &mut Vec<N>,
-        g_cost: C,
-        threshold: C,
-        init_state: S,
-        change_state: &CS,
-        is_redundant: &IR,
-        complexity: &mut Complexity,
-        depth: usize,
-    ) -> Res<C>
-    where
-        N: Clone,
-        C: Zero + Ord + Copy + Debug,
-        FN: Fn(&N) -> IN,
-        IN: IntoIterator<Item = (A, C)>,
-        FH: Fn(&N) -> C,
-        FS: Fn(&N) -> bool,
-        FA: Fn(&N, A) -> N,
-        S: Copy,
-        CS: Fn(&S, A) -> S,
-        IR: Fn(&S) -> bool,
-        A: Copy,
-    {
-        if success(&start) {
-            path.push(start);
+    search(
+        &Closures::new(
+            neighbours_actions,
+            perform_action,
+            heuristic,
+            success,
+            init_state,
+            change_state,
+            is_redundant,
+        ),
+        start,
+    )
+}
+
+fn aux<P: SearchProblem>(
+    problem: &P,
+    start: P::State,
+    path: &mut Vec<P::State>,
+    g_cost: P::Cost,
+    threshold: P::Cost,
+    init_state: P::Pruning,
+    complexity: &mut Complexity,
+    depth: usize,
+    stop: &AtomicBool,
+) -> Res<P::Cost> {
+    if stop.load(AtomicOrdering::Relaxed) || problem.interrupted() {
+        return Interrupted;
+    }
+    if problem.is_goal(&start) {
+        path.push(start);
+        return Found;
+    }
+    if depth > complexity.in_size {
+        complexity.in_size = depth;
+    }
+    let mut min_fcost = P::Cost::zero();
+    let f_cost = g_cost + problem.heuristic(&start);
+    if f_cost > threshold {
+        return MinFCost(f_cost);
+    }
+    for (a, c) in problem.successors(&start) {
+        let new_state = problem.next_pruning(&init_state, a);
+        if problem.is_pruned(&new_state) {
+            continue;
+        }
+        let n = problem.apply(&start, a);
+        complexity.in_time += 1;
+        match aux(
+            problem,
+            n,
+            path,
+            g_cost + c,
+            threshold,
+            new_state,
+            complexity,
+            depth + 1,
+            stop,
+        ) {
+            Found => {
+                path.push(start);
+                return Found;
+            }
+            MinFCost(c) => {
+                if min_fcost == P::Cost::zero() || (c < min_fcost && c != P::Cost::zero()) {
+                    min_fcost = c;
+                }
+            }
+            Interrupted => return Interrupted,
+        }
+    }
+    return MinFCost(min_fcost);
+}
+
+pub fn search<P: SearchProblem>(
+    problem: &P,
+    start: &P::State,
+) -> Option<(Vec<P::State>, Complexity)> {
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 0,
+    };
+    let mut threshold = problem.heuristic(start);
+    let mut path = Vec::new();
+    let stop = AtomicBool::new(false);
+    loop {
+        problem.progress(Some(threshold), 0);
+        match aux(
+            problem,
+            start.clone(),
+            &mut path,
+            P::Cost::zero(),
+            threshold,
+            problem.initial_pruning(),
+            &mut complexity,
+            1,
+            &stop,
+        ) {
+            Found => break,
+            MinFCost(new_threshold) => threshold = new_threshold,
+            Interrupted => return None,
+        }
+    }
+    //TODO: See final cost
+    return Some((path, complexity));
+}
+
+/// Every solution of optimal cost, as the actions taken from the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllSolutions<A, C> {
+    pub cost: C,
+    /// Empty when only counting
+    pub solutions: Vec<Vec<A>>,
+    pub nb_solutions: usize,
+    /// Whether the search stopped at `max_solutions`
+    pub truncated: bool,
+    pub complexity: Complexity,
+}
+
+/// What `aux_all` collected so far. Distinct paths of the tree search are
+/// distinct action sequences, no solution is found twice.
+struct Collector<A> {
+    solutions: Vec<Vec<A>>,
+    nb_solutions: usize,
+    max_solutions: Option<usize>,
+    count_only: bool,
+}
+
+impl<A: Clone> Collector<A> {
+    /// Record a solution, true once enough were found
+    fn add(&mut self, actions: &[A]) -> bool {
+        if !self.count_only {
+            self.solutions.push(actions.to_vec());
+        }
+        self.nb_solutions += 1;
+        self.max_solutions.map_or(false, |max| self.nb_solutions >= max)
+    }
+}
+
+/// Same as `aux` but goes on after the solutions, `Found` means enough were
+/// collected
+fn aux_all<P>(
+    problem: &P,
+    start: &P::State,
+    actions: &mut Vec<P::Action>,
+    g_cost: P::Cost,
+    threshold: P::Cost,
+    init_state: P::Pruning,
+    complexity: &mut Complexity,
+    collector: &mut Collector<P::Action>,
+) -> Res<P::Cost>
+where
+    P: SearchProblem,
+{
+    if problem.interrupted() {
+        return Interrupted;
+    }
+    if problem.is_goal(start) {
+        if collector.add(actions) {
             return Found;
         }
-        if depth > complexity.in_size {
-            complexity.in_size = depth;
+        // going through the goal can't be optimal
+        return MinFCost(P::Cost::zero());
+    }
+    if actions.len() + 1 > complexity.in_size {
+        complexity.in_size = actions.len() + 1;
+    }
+    let f_cost = g_cost + problem.heuristic(start);
+    if f_cost > threshold {
+        return MinFCost(f_cost);
+    }
+    let mut min_fcost = P::Cost::zero();
+    for (a, c) in problem.successors(start) {
+        let new_state = problem.next_pruning(&init_state, a);
+        if problem.is_pruned(&new_state) {
+            continue;
+        }
+        let n = problem.apply(start, a);
+        complexity.in_time += 1;
+        actions.push(a);
+        let res = aux_all(
+            problem,
+            &n,
+            actions,
+            g_cost + c,
+            threshold,
+            new_state,
+            complexity,
+            collector,
+        );
+        actions.pop();
+        match res {
+            MinFCost(c) => {
+                if min_fcost == P::Cost::zero() || (c < min_fcost && c != P::Cost::zero()) {
+                    min_fcost = c;
+                }
+            }
+            res => return res,
+        }
+    }
+    MinFCost(min_fcost)
+}
+
+/// Every optimal solution, or only their number with `count_only`, stopping
+/// at `max_solutions`, None when it is `Some(0)`. The iteration that finds the
+/// first solution goes on with the same threshold to find the others.
+///
+/// The pruning must not cut any optimal path: with a pruning automaton only
+/// one of the equivalent optimal paths would be found, forbidding to undo the
+/// last move is fine.
+pub fn search_all<P>(
+    problem: &P,
+    start: &P::State,
+    max_solutions: Option<usize>,
+    count_only: bool,
+) -> Option<AllSolutions<P::Action, P::Cost>>
+where
+    P: SearchProblem,
+{
+    if max_solutions == Some(0) {
+        return None;
+    }
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 0,
+    };
+    let mut collector = Collector {
+        solutions: Vec::new(),
+        nb_solutions: 0,
+        max_solutions,
+        count_only,
+    };
+    let mut threshold = problem.heuristic(start);
+    loop {
+        problem.progress(Some(threshold), 0);
+        let res = aux_all(
+            problem,
+            start,
+            &mut Vec::new(),
+            P::Cost::zero(),
+            threshold,
+            problem.initial_pruning(),
+            &mut complexity,
+            &mut collector,
+        );
+        match res {
+            Interrupted => return None,
+            _ if collector.nb_solutions > 0 => break,
+            MinFCost(c) if c != P::Cost::zero() => threshold = c,
+            _ => return None,
+        }
+    }
+    Some(AllSolutions {
+        cost: threshold,
+        truncated: max_solutions.map_or(false, |max| collector.nb_solutions >= max),
+        solutions: collector.solutions,
+        nb_solutions: collector.nb_solutions,
+        complexity,
+    })
+}
+
+/// Progress of `search_resumable`, saved to go on after the process is killed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint<C> {
+    /// Hash of the start, to refuse resuming the search of another one
+    start: u64,
+    /// What the search was toward, the explored subtrees may hide solutions
+    /// of another goal or heuristic
+    goal: u64,
+    heuristic: String,
+    pub threshold: C,
+    /// Rank among the successors of each state from the start to the next
+    /// subtree to explore in this iteration, the subtrees on their left are
+    /// explored
+    pub position: Vec<usize>,
+    /// Lowest f-cost above the threshold in the explored subtrees, zero if
+    /// there is none yet
+    pub min_fcost: C,
+    pub complexity: Complexity,
+    /// Time spent by the runs so far
+    pub elapsed: Duration,
+}
+
+impl<C: Serialize + DeserializeOwned> Checkpoint<C> {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        // appended rather than replacing the extension, `run.tmp` would be
+        // written in place otherwise
+        let mut tmp = path.as_ref().as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, serialize(self)?)?;
+        // renamed so that a kill never leaves half a checkpoint
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(deserialize(&fs::read(path)?[..])?)
+    }
+}
+
+/// Where and how often `aux_resumable` saves its progress
+struct Saver<'a, C> {
+    file: &'a Path,
+    interval: Duration,
+    checkpoint: Checkpoint<C>,
+    /// Successor ranks of the current path
+    stack: Vec<usize>,
+    started: Instant,
+    last_save: Instant,
+    error: Option<Box<dyn Error>>,
+}
+
+impl<'a, C: Serialize + DeserializeOwned + Copy> Saver<'a, C> {
+    /// Save the position of the subtree about to be explored
+    fn save(&mut self) -> bool {
+        self.checkpoint.position = self.stack.clone();
+        self.checkpoint.elapsed += self.started.elapsed();
+        self.started = Instant::now();
+        self.last_save = self.started;
+        if let Err(e) = self.checkpoint.save(self.file) {
+            self.error = Some(e);
+            return false;
+        }
+        true
+    }
+}
+
+/// Same as `aux` but skips the subtrees on the left of `resume`, and saves
+/// the position every so often. The f-costs above the threshold go to the
+/// checkpoint instead of being returned.
+fn aux_resumable<P>(
+    problem: &P,
+    start: P::State,
+    path: &mut Vec<P::State>,
+    g_cost: P::Cost,
+    init_state: P::Pruning,
+    resume: Option<&[usize]>,
+    saver: &mut Saver<P::Cost>,
+) -> Res<P::Cost>
+where
+    P: SearchProblem,
+    P::Cost: Serialize + DeserializeOwned,
+{
+    if problem.interrupted() {
+        return Interrupted;
+    }
+    if problem.is_goal(&start) {
+        path.push(start);
+        return Found;
+    }
+    let checkpoint = &mut saver.checkpoint;
+    if saver.stack.len() + 1 > checkpoint.complexity.in_size {
+        checkpoint.complexity.in_size = saver.stack.len() + 1;
+    }
+    let f_cost = g_cost + problem.heuristic(&start);
+    if f_cost > checkpoint.threshold {
+        if checkpoint.min_fcost == P::Cost::zero() || f_cost < checkpoint.min_fcost {
+            checkpoint.min_fcost = f_cost;
+        }
+        return MinFCost(f_cost);
+    }
+    let skip = resume.and_then(|resume| resume.first().cloned()).unwrap_or(0);
+    for (i, (a, c)) in problem.successors(&start).into_iter().enumerate().skip(skip) {
+        let new_state = problem.next_pruning(&init_state, a);
+        if problem.is_pruned(&new_state) {
+            continue;
         }
-        let mut min_fcost = C::zero();
-        let f_cost = g_cost + heuristic(&start);
-        if f_cost > threshold {
-            return MinFCost(f_cost);
+        let n = problem.apply(&start, a);
+        saver.checkpoint.complexity.in_time += 1;
+        saver.stack.push(i);
+        if saver.last_save.elapsed() >= saver.interval && !saver.save() {
+            return Interrupted;
         }
-        for (a, c) in neighbours_actions(&start) {
-            let new_state = change_state(&init_state, a);
-            if is_redundant(&new_state) {
-                continue;
+        let resume = match resume {
+            Some(resume) if i == skip && !resume.is_empty() => Some(&resume[1..]),
+            _ => None,
+        };
+        match aux_resumable(problem, n, path, g_cost + c, new_state, resume, saver) {
+            Found => {
+                path.push(start);
+                return Found;
             }
-            let n = perform_action(&start, a);
-            complexity.in_time += 1;
-            match aux(
-                n,
-                neighbours_actions,
-                perform_action,
-                heuristic,
-                success,
-                path,
-                g_cost + c,
-                threshold,
-                new_state,
-                change_state,
-                is_redundant,
-                complexity,
-                depth + 1,
-            ) {
-                Found => {
-                    path.push(start);
-                    return Found;
+            MinFCost(_) => saver.stack.pop(),
+            // the path to the interrupted subtree is saved
+            Interrupted => return Interrupted,
+        };
+    }
+    MinFCost(saver.checkpoint.min_fcost)
+}
+
+/// Same as `search` but the progress is saved to `file` every `interval`,
+/// at the end of every iteration and when the search is interrupted. With
+/// `resume` the search goes on from the checkpoint in `file` instead of
+/// starting again at the heuristic of the start: the problem must be the
+/// same as when it was saved, `goal` and `heuristic` identify it and a
+/// checkpoint saved with others is refused. The checkpoint is removed once a
+/// solution is found.
+pub fn search_resumable<P>(
+    problem: &P,
+    start: &P::State,
+    goal: u64,
+    heuristic: &str,
+    file: &Path,
+    interval: Duration,
+    resume: bool,
+) -> Result<Option<(Vec<P::State>, Complexity)>, Box<dyn Error>>
+where
+    P: SearchProblem,
+    P::State: Hash,
+    P::Cost: Serialize + DeserializeOwned,
+{
+    let start_hash = BuildZobristHasher::default().hash_one(start);
+    let checkpoint = if resume {
+        let checkpoint = Checkpoint::load(file)?;
+        if checkpoint.start != start_hash {
+            return Err(From::from("the checkpoint is for another taquin"));
+        }
+        if checkpoint.goal != goal {
+            return Err(From::from("the checkpoint is toward another goal"));
+        }
+        if checkpoint.heuristic != heuristic {
+            return Err(From::from(format!(
+                "the checkpoint was saved with the {} heuristic",
+                checkpoint.heuristic
+            )));
+        }
+        checkpoint
+    } else {
+        Checkpoint {
+            start: start_hash,
+            goal,
+            heuristic: heuristic.to_string(),
+            threshold: problem.heuristic(start),
+            position: Vec::new(),
+            min_fcost: P::Cost::zero(),
+            complexity: Complexity {
+                in_time: 0,
+                in_size: 0,
+            },
+            elapsed: Duration::from_secs(0),
+        }
+    };
+    let mut saver = Saver {
+        file,
+        interval,
+        checkpoint,
+        stack: Vec::new(),
+        started: Instant::now(),
+        last_save: Instant::now(),
+        error: None,
+    };
+    let mut path = Vec::new();
+    loop {
+        let resume = saver.checkpoint.position.clone();
+        problem.progress(Some(saver.checkpoint.threshold), 0);
+        match aux_resumable(
+            problem,
+            start.clone(),
+            &mut path,
+            P::Cost::zero(),
+            problem.initial_pruning(),
+            Some(&resume),
+            &mut saver,
+        ) {
+            Found => break,
+            MinFCost(min_fcost) => {
+                if min_fcost == P::Cost::zero() {
+                    let _ = fs::remove_file(file);
+                    return Ok(None);
                 }
-                MinFCost(c) => {
-                    if min_fcost == C::zero() || (c < min_fcost && c != C::zero()) {
-                        min_fcost = c;
-                    }
+                let checkpoint = &mut saver.checkpoint;
+                checkpoint.threshold = min_fcost;
+                checkpoint.min_fcost = P::Cost::zero();
+                if !saver.save() {
+                    break;
+                }
+            }
+            Interrupted => {
+                if saver.error.is_none() {
+                    saver.save();
                 }
+                break;
             }
         }
-        return MinFCost(min_fcost);
     }
+    if let Some(e) = saver.error {
+        return Err(e);
+    }
+    if path.is_empty() {
+        return Ok(None);
+    }
+    fs::remove_file(file).ok();
+    Ok(Some((path, saver.checkpoint.complexity)))
+}
+
+/// Subtree explored by one thread during an iteration of `search_parallel`
+struct Task<P: SearchProblem> {
+    /// States from the start to the parent of `state`
+    prefix: Vec<P::State>,
+    state: P::State,
+    g_cost: P::Cost,
+    pruning: P::Pruning,
+}
+
+/// Same as `search` but splits every iteration into subtrees shared between
+/// `threads` threads. The first solution found within the threshold is
+/// returned, which keeps it optimal. None if one of the threads panicked.
+pub fn search_parallel<P>(
+    problem: &P,
+    start: &P::State,
+    threads: usize,
+) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem + Sync,
+    P::State: Send + Sync,
+    P::Cost: Send + Sync,
+    P::Pruning: Send + Sync,
+{
+    const TASKS_PER_THREAD: usize = 8;
+    const MAX_SPLIT_DEPTH: usize = 8;
 
     let mut complexity = Complexity {
         in_time: 0,
         in_size: 0,
     };
-    let mut threshold = heuristic(start);
-    let mut path = Vec::new();
-    while let MinFCost(new_threshold) = aux(
-        start.clone(),
-        &neighbours_actions,
-        &perform_action,
-        &heuristic,
-        &success,
-        &mut path,
-        C::zero(),
-        threshold,
-        init_state,
-        &change_state,
-        &is_redundant,
-        &mut complexity,
-        1,
-    ) {
-        threshold = new_threshold;
+    let mut threshold = problem.heuristic(start);
+    loop {
+        if problem.interrupted() {
+            return None;
+        }
+        problem.progress(Some(threshold), 0);
+        // Expand the top of the tree breadth first until there are enough subtrees
+        let mut min_fcost = P::Cost::zero();
+        let mut tasks = vec![Task::<P> {
+            prefix: Vec::new(),
+            state: start.clone(),
+            g_cost: P::Cost::zero(),
+            pruning: problem.initial_pruning(),
+        }];
+        let mut depth = 1;
+        while tasks.len() < threads * TASKS_PER_THREAD && depth < MAX_SPLIT_DEPTH {
+            if tasks.iter().any(|task| problem.is_goal(&task.state)) {
+                break;
+            }
+            let mut next_tasks = Vec::new();
+            for task in tasks {
+                let f_cost = task.g_cost + problem.heuristic(&task.state);
+                if f_cost > threshold {
+                    if min_fcost == P::Cost::zero() || f_cost < min_fcost {
+                        min_fcost = f_cost;
+                    }
+                    continue;
+                }
+                for (a, c) in problem.successors(&task.state) {
+                    let pruning = problem.next_pruning(&task.pruning, a);
+                    if problem.is_pruned(&pruning) {
+                        continue;
+                    }
+                    complexity.in_time += 1;
+                    let mut prefix = task.prefix.clone();
+                    prefix.push(task.state.clone());
+                    next_tasks.push(Task {
+                        prefix,
+                        state: problem.apply(&task.state, a),
+                        g_cost: task.g_cost + c,
+                        pruning,
+                    });
+                }
+            }
+            tasks = next_tasks;
+            depth += 1;
+        }
+
+        let next_task = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let joined: Vec<Option<_>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut local_complexity = Complexity {
+                            in_time: 0,
+                            in_size: 0,
+                        };
+                        let mut local_min_fcost = P::Cost::zero();
+                        loop {
+                            let task = match tasks.get(next_task.fetch_add(1, AtomicOrdering::Relaxed)) {
+                                Some(task) => task,
+                                None => break,
+                            };
+                            let mut path = Vec::new();
+                            match aux(
+                                problem,
+                                task.state.clone(),
+                                &mut path,
+                                task.g_cost,
+                                threshold,
+                                task.pruning,
+                                &mut local_complexity,
+                                task.prefix.len() + 1,
+                                &stop,
+                            ) {
+                                Found => {
+                                    stop.store(true, AtomicOrdering::Relaxed);
+                                    path.extend(task.prefix.iter().rev().cloned());
+                                    return (Some(path), local_min_fcost, local_complexity);
+                                }
+                                MinFCost(c) => {
+                                    if local_min_fcost == P::Cost::zero()
+                                        || (c < local_min_fcost && c != P::Cost::zero())
+                                    {
+                                        local_min_fcost = c;
+                                    }
+                                }
+                                Interrupted => break,
+                            }
+                        }
+                        (None, local_min_fcost, local_complexity)
+                    })
+                }).collect();
+            // every thread is joined before giving up, the scope would panic otherwise
+            handles.into_iter().map(|handle| handle.join().ok()).collect()
+        });
+        let results: Vec<(Option<Vec<P::State>>, P::Cost, Complexity)> =
+            joined.into_iter().collect::<Option<_>>()?;
+
+        let mut solution = None;
+        for (path, c, local_complexity) in results {
+            complexity.in_time += local_complexity.in_time;
+            if local_complexity.in_size > complexity.in_size {
+                complexity.in_size = local_complexity.in_size;
+            }
+            if min_fcost == P::Cost::zero() || (c < min_fcost && c != P::Cost::zero()) {
+                min_fcost = c;
+            }
+            if solution.is_none() {
+                solution = path;
+            }
+        }
+        if let Some(path) = solution {
+            return Some((path, complexity));
+        }
+        if problem.interrupted() || min_fcost == P::Cost::zero() {
+            return None;
+        }
+        threshold = min_fcost;
     }
-    //TODO: See final cost
-    return Some((path, complexity));
 }
@@ -1,14 +1,33 @@
 pub mod astar;
+pub mod beam_search;
+pub mod breadth_first;
 pub mod complexity;
 pub mod construct_pruning_trie;
+pub mod depth_first;
+pub mod dijkstra;
+pub mod disk_bfs;
+pub mod distance_table;
+pub mod flat_trie;
+pub mod frontier_astar;
 pub mod greedy_search;
+pub mod heuristic;
+pub mod heuristic_check;
 pub mod idastar;
+pub mod lrta_star;
 #[allow(non_snake_case)]
 pub mod maxHeap;
 pub mod maxdir;
+pub mod pruning;
+pub mod rank;
+pub mod rbfs;
+pub mod reduction;
+pub mod search_problem;
+pub mod sma_star;
+pub mod solver;
 pub mod taquin;
 pub mod trie;
 pub mod visualizable;
+pub mod zobrist;
 
 extern crate sdl2;
 #[macro_use]
@@ -19,6 +38,8 @@ extern crate num_traits;
 #[macro_use]
 extern crate derive_new;
 extern crate rand;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate bincode;
+extern crate memmap;
@@ -0,0 +1,313 @@
+use bincode::{deserialize, serialize};
+use heuristic::Heuristic;
+use pruning::cache_dir;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use taquin::{Dir, Taquin};
+
+/// Time the agent thinks before each move when the caller sets none
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(100);
+
+/// Deepest lookahead when the caller sets none
+pub const DEFAULT_LOOKAHEAD: usize = 32;
+
+/// Board of a learned value: its rank up to 4x4, the whole board above, so
+/// that two boards never share a value
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum BoardKey {
+    Rank(u64),
+    Pieces(Vec<u64>),
+}
+
+impl BoardKey {
+    fn of(taquin: &Taquin) -> Self {
+        match taquin.rank() {
+            Some(rank) => BoardKey::Rank(rank),
+            None => BoardKey::Pieces(taquin.iter().cloned().collect()),
+        }
+    }
+}
+
+/// Values learned by an agent above its heuristic
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LearnedTable {
+    goal: Vec<u64>,
+    values: HashMap<BoardKey, u64>,
+}
+
+impl LearnedTable {
+    pub fn new(goal: &Taquin) -> Self {
+        LearnedTable {
+            goal: goal.iter().cloned().collect(),
+            values: HashMap::default(),
+        }
+    }
+
+    /// Whether the values are distances toward `goal`
+    pub fn is_for(&self, goal: &Taquin) -> bool {
+        goal.iter().eq(self.goal.iter())
+    }
+
+    pub fn get(&self, taquin: &Taquin) -> Option<u64> {
+        self.values.get(&BoardKey::of(taquin)).cloned()
+    }
+
+    /// Number of boards learned
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serialize(self)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(deserialize(&fs::read(path)?[..])?)
+    }
+
+    /// Table toward `goal` from the user cache, an empty one if there is none
+    pub fn load_or_new(goal: &Taquin) -> Self {
+        cache_file(goal)
+            .and_then(|file| LearnedTable::load(file).ok())
+            .filter(|table| table.is_for(goal))
+            .unwrap_or_else(|| LearnedTable::new(goal))
+    }
+
+    /// Save the table in the user cache for the next sessions
+    pub fn save_in_cache(&self) -> Result<(), Box<dyn Error>> {
+        let goal = Taquin::new((self.goal.len() as f64).sqrt() as usize, self.goal.clone());
+        let file = cache_file(&goal).ok_or("no cache directory")?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.save(file)
+    }
+}
+
+fn cache_file(goal: &Taquin) -> Option<PathBuf> {
+    let n = goal.dim();
+    cache_dir().map(|dir| dir.join(format!("learned_{}x{}_{:016x}.bin", n, n, goal.zobrist())))
+}
+
+/// Learning real-time astar: before each move the agent looks ahead as deep
+/// as its time budget allows, raises the value of the board to the best one
+/// found, and moves toward it. Always reaches the goal of a solvable board,
+/// and repeated trials with the same table converge to optimal moves.
+pub struct Agent<'a> {
+    goal: Taquin,
+    heuristic: &'a dyn Heuristic,
+    table: LearnedTable,
+    budget: Duration,
+    lookahead: usize,
+    /// Board played by the iterator
+    current: Taquin,
+}
+
+impl<'a> Agent<'a> {
+    /// Agent playing `taquin` toward `goal`, with an empty table
+    pub fn new(taquin: Taquin, goal: Taquin, heuristic: &'a dyn Heuristic) -> Self {
+        Agent {
+            table: LearnedTable::new(&goal),
+            goal,
+            heuristic,
+            budget: DEFAULT_BUDGET,
+            lookahead: DEFAULT_LOOKAHEAD,
+            current: taquin,
+        }
+    }
+
+    /// Time spent looking ahead before each move, a lookahead of one move is
+    /// always completed
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Deepest lookahead, whatever the budget
+    pub fn lookahead(mut self, depth: usize) -> Self {
+        self.lookahead = depth.max(1);
+        self
+    }
+
+    /// Start from values learned before, ignored if they are for another goal
+    pub fn table(mut self, table: LearnedTable) -> Self {
+        if table.is_for(&self.goal) {
+            self.table = table;
+        }
+        self
+    }
+
+    pub fn learned(&self) -> &LearnedTable {
+        &self.table
+    }
+
+    pub fn into_table(self) -> LearnedTable {
+        self.table
+    }
+
+    /// Board played by the iterator
+    pub fn board(&self) -> &Taquin {
+        &self.current
+    }
+
+    fn value(&self, taquin: &Taquin) -> u64 {
+        let h = self.heuristic.evaluate(taquin, &self.goal);
+        self.table.get(taquin).map_or(h, |learned| learned.max(h))
+    }
+
+    /// Best value of the boards `depth` moves below `taquin`, None when out of
+    /// time. Subtrees whose f-cost is above `alpha` are cut.
+    fn minimin(
+        &self,
+        taquin: &Taquin,
+        last: Dir,
+        g: u64,
+        depth: usize,
+        alpha: &mut u64,
+        deadline: Option<Instant>,
+    ) -> Option<u64> {
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            return None;
+        }
+        if taquin.is_solved(&self.goal) {
+            *alpha = (*alpha).min(g);
+            return Some(g);
+        }
+        let f = g + self.value(taquin);
+        if f > *alpha {
+            return Some(f);
+        }
+        if depth == 0 {
+            *alpha = f;
+            return Some(f);
+        }
+        let mut best = ::std::u64::MAX;
+        for dir in taquin.neighbours() {
+            if dir == last.oposite() {
+                continue;
+            }
+            let next = taquin.move_piece(dir).unwrap();
+            best = best.min(self.minimin(&next, dir, g + 1, depth - 1, alpha, deadline)?);
+        }
+        Some(best.max(f))
+    }
+
+    /// Move to play from `taquin`, None if it is solved. What the lookahead
+    /// found is learned.
+    pub fn choose(&mut self, taquin: &Taquin) -> Option<Dir> {
+        if taquin.is_solved(&self.goal) {
+            return None;
+        }
+        let deadline = Instant::now() + self.budget;
+        let mut best = None;
+        for depth in 1..=self.lookahead {
+            // the first lookahead always completes so that there is a move
+            let deadline = if depth == 1 { None } else { Some(deadline) };
+            let mut alpha = ::std::u64::MAX;
+            let values = taquin
+                .neighbours()
+                .into_iter()
+                .map(|dir| {
+                    let next = taquin.move_piece(dir).unwrap();
+                    self.minimin(&next, dir, 1, depth - 1, &mut alpha, deadline)
+                        .map(|value| (value, dir))
+                })
+                .collect::<Option<Vec<_>>>();
+            match values {
+                Some(values) => best = values.into_iter().min_by_key(|&(value, _)| value),
+                None => break,
+            }
+        }
+        let (value, dir) = best?;
+        if value > self.value(taquin) {
+            self.table.values.insert(BoardKey::of(taquin), value);
+        }
+        Some(dir)
+    }
+}
+
+/// Moves of one trial from the board given to `Agent::new`
+impl<'a> Iterator for Agent<'a> {
+    type Item = Dir;
+
+    fn next(&mut self) -> Option<Dir> {
+        let current = self.current.clone();
+        let dir = self.choose(&current)?;
+        self.current = current.move_piece(dir).unwrap();
+        Some(dir)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar;
+    use heuristic::Manhattan;
+    use search_problem::TaquinProblem;
+    use std::env;
+
+    #[test]
+    fn solves() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let mut agent =
+            Agent::new(taquin.clone(), spiral.clone(), &Manhattan).budget(Duration::from_millis(1));
+        let moves = agent.by_ref().take(10_000).count();
+        assert!(moves < 10_000);
+        assert_eq!(agent.board(), &spiral);
+        assert_eq!(agent.next(), None);
+    }
+    #[test]
+    fn learns() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![0, 1, 2, 6, 4, 3, 5, 8, 7]);
+        let (optimal, _) =
+            astar::search(&TaquinProblem::new(spiral.clone(), &Manhattan), &taquin).unwrap();
+        // without time limit so that every trial looks 2 moves ahead
+        let mut table = LearnedTable::new(&spiral);
+        let mut trials = Vec::new();
+        for _ in 0..100 {
+            let mut agent = Agent::new(taquin.clone(), spiral.clone(), &Manhattan)
+                .budget(Duration::from_secs(3600))
+                .lookahead(2)
+                .table(table);
+            trials.push(agent.by_ref().count());
+            table = agent.into_table();
+        }
+        assert!(!table.is_empty());
+        assert_eq!(trials.last(), Some(&(optimal.len() - 1)));
+        assert!(trials.iter().all(|&moves| moves >= optimal.len() - 1));
+
+        let file = env::temp_dir().join(format!("npuzzle-learned-{}", ::std::process::id()));
+        table.save(&file).unwrap();
+        assert_eq!(LearnedTable::load(&file).unwrap(), table);
+        fs::remove_file(file).unwrap();
+        let other = Agent::new(
+            taquin,
+            Taquin::new(3, vec![1, 2, 3, 4, 5, 6, 7, 8, 0]),
+            &Manhattan,
+        );
+        assert!(other.table(table).learned().is_empty());
+    }
+    #[test]
+    fn keys() {
+        let spiral = Taquin::spiral(3);
+        assert_eq!(BoardKey::of(&spiral), BoardKey::Rank(spiral.rank().unwrap()));
+        // above 4x4 the boards are kept whole
+        let big = Taquin::spiral(5);
+        let moved = big.move_piece(big.neighbours()[0]).unwrap();
+        assert_eq!(BoardKey::of(&big), BoardKey::Pieces(big.iter().cloned().collect()));
+        let mut table = LearnedTable::new(&big);
+        table.values.insert(BoardKey::of(&moved), 7);
+        assert_eq!(table.get(&moved), Some(7));
+        assert_eq!(table.get(&big), None);
+    }
+}
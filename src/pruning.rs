@@ -0,0 +1,395 @@
+use bincode::{deserialize, serialize};
+use construct_pruning_trie::build_pruning_trie;
+use std::cmp::min;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use taquin::Dir;
+use flat_trie::FlatTrie;
+use trie::{Automaton, Trie, TrieError, TrieType};
+
+/// Depth of the automata built when none is given
+pub const DEFAULT_DEPTH: usize = 10;
+
+/// First bytes of every automaton file
+pub const MAGIC: &[u8; 8] = b"NPZPRUNE";
+
+/// Bumped whenever the header or the layout of `Trie` changes
+pub const FORMAT_VERSION: u32 = 1;
+
+// magic, version, dim, depth, nb_nodes, checksum
+const HEADER_LEN: usize = 8 + 4 + 4 + 4 + 8 + 8;
+
+/// Automata of depth `depth` are the same for every dimension above
+/// `depth + 1`, the blank can't reach the border anymore
+pub fn normalized_dim(dim: usize, depth: usize) -> usize {
+    min(dim, depth + 1)
+}
+
+#[derive(Debug)]
+pub enum AutomatonFileError {
+    Io(io::Error),
+    /// Not an automaton file, or a raw one from before the header
+    BadMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    BadChecksum,
+    Decode(::bincode::Error),
+    /// A transition leaves the automaton or the node count is wrong
+    Corrupted,
+    /// A transition of this node of a flat automaton leaves the automaton
+    BadTransition(usize),
+    /// Built for boards of dimension `found` with `depth`, used for `expected`
+    WrongSize {
+        expected: usize,
+        found: usize,
+        depth: usize,
+    },
+}
+
+impl fmt::Display for AutomatonFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AutomatonFileError::Io(ref e) => write!(f, "{}", e),
+            AutomatonFileError::BadMagic => write!(
+                f,
+                "not a prunning automaton, files of older versions can be converted with prune --migrate"
+            ),
+            AutomatonFileError::UnsupportedVersion(v) => write!(
+                f,
+                "prunning automaton format version {} is not supported (expected {})",
+                v, FORMAT_VERSION
+            ),
+            AutomatonFileError::Truncated => write!(f, "truncated prunning automaton"),
+            AutomatonFileError::BadChecksum => write!(f, "bad checksum in prunning automaton"),
+            AutomatonFileError::Decode(ref e) => write!(f, "{}", e),
+            AutomatonFileError::Corrupted => write!(f, "corrupted prunning automaton"),
+            AutomatonFileError::BadTransition(node) => write!(
+                f,
+                "corrupted prunning automaton: a transition of node {} leaves it",
+                node
+            ),
+            AutomatonFileError::WrongSize {
+                expected,
+                found,
+                depth,
+            } => write!(
+                f,
+                "prunning automaton built for {}x{} (depth {}) can't be used on {}x{}",
+                found, found, depth, expected, expected
+            ),
+        }
+    }
+}
+
+impl Error for AutomatonFileError {
+    fn description(&self) -> &str {
+        match *self {
+            AutomatonFileError::Io(_) => "can't read the prunning automaton",
+            AutomatonFileError::BadMagic => "not a prunning automaton",
+            AutomatonFileError::UnsupportedVersion(_) => "unsupported format version",
+            AutomatonFileError::Truncated => "truncated prunning automaton",
+            AutomatonFileError::BadChecksum => "bad checksum",
+            AutomatonFileError::Decode(_) => "can't decode the prunning automaton",
+            AutomatonFileError::Corrupted => "corrupted prunning automaton",
+            AutomatonFileError::BadTransition(_) => "transition out of the prunning automaton",
+            AutomatonFileError::WrongSize { .. } => "prunning automaton of the wrong size",
+        }
+    }
+}
+
+impl From<io::Error> for AutomatonFileError {
+    fn from(error: io::Error) -> Self {
+        AutomatonFileError::Io(error)
+    }
+}
+
+impl From<::bincode::Error> for AutomatonFileError {
+    fn from(error: ::bincode::Error) -> Self {
+        AutomatonFileError::Decode(error)
+    }
+}
+
+/// FNV-1a of the encoded trie
+pub(crate) fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+/// A pruning trie along with what it was built for
+#[derive(Clone, Debug, PartialEq)]
+pub struct PruningAutomaton {
+    pub dim: usize,
+    pub depth: usize,
+    pub trie: Trie,
+}
+
+impl PruningAutomaton {
+    /// Build and minimise the automaton
+    pub fn build(dim: usize, depth: usize) -> Result<Self, TrieError> {
+        let dim = normalized_dim(dim, depth);
+        Ok(PruningAutomaton {
+            dim,
+            depth,
+            trie: build_pruning_trie(dim, depth, 1, |_| ())?.minimise(),
+        })
+    }
+
+    /// Whether the automaton prunes correctly the boards of dimension `dim`
+    pub fn is_for(&self, dim: usize) -> bool {
+        normalized_dim(dim, self.depth) == normalized_dim(self.dim, self.depth)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AutomatonFileError> {
+        let body = serialize(&self.trie)?;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.dim as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.depth as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.trie.0.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&checksum(&body).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AutomatonFileError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(AutomatonFileError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(AutomatonFileError::Truncated);
+        }
+        let version = read_u32(&bytes[8..]);
+        if version != FORMAT_VERSION {
+            return Err(AutomatonFileError::UnsupportedVersion(version));
+        }
+        let dim = read_u32(&bytes[12..]) as usize;
+        let depth = read_u32(&bytes[16..]) as usize;
+        let nb_nodes = read_u64(&bytes[20..]) as usize;
+        let body = &bytes[HEADER_LEN..];
+        if checksum(body) != read_u64(&bytes[28..]) {
+            return Err(AutomatonFileError::BadChecksum);
+        }
+        let trie: Trie = deserialize(body)?;
+        let in_bounds = |t: &TrieType| match *t {
+            TrieType::Redundant => true,
+            TrieType::Failure(s) | TrieType::Match(s) => s < nb_nodes,
+        };
+        let dirs = [Dir::Right, Dir::Up, Dir::Down, Dir::Left];
+        if nb_nodes == 0
+            || trie.0.len() != nb_nodes
+            || !trie.0.iter().all(|node| dirs.iter().all(|&d| in_bounds(&node[d])))
+        {
+            return Err(AutomatonFileError::Corrupted);
+        }
+        Ok(PruningAutomaton { dim, depth, trie })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AutomatonFileError> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AutomatonFileError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Load an automaton and check it can prune boards of dimension `dim`
+    pub fn load_for<P: AsRef<Path>>(path: P, dim: usize) -> Result<Self, AutomatonFileError> {
+        let automaton = Self::load(path)?;
+        if !automaton.is_for(dim) {
+            return Err(AutomatonFileError::WrongSize {
+                expected: dim,
+                found: automaton.dim,
+                depth: automaton.depth,
+            });
+        }
+        Ok(automaton)
+    }
+
+    /// Read a raw bincode `Trie`, as written before the header existed. The
+    /// dimension and depth it was built for must be given.
+    pub fn migrate<P: AsRef<Path>>(
+        path: P,
+        dim: usize,
+        depth: usize,
+    ) -> Result<Self, AutomatonFileError> {
+        let trie = deserialize(&fs::read(path)?)?;
+        Ok(PruningAutomaton { dim, depth, trie })
+    }
+}
+
+/// `$XDG_CACHE_HOME/npuzzle`, or `$HOME/.cache/npuzzle`
+pub fn cache_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => Some(PathBuf::from(dir).join("npuzzle")),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("npuzzle")),
+    }
+}
+
+fn file_name(dim: usize, depth: usize) -> String {
+    let dim = normalized_dim(dim, depth);
+    format!("prunning_automaton_{}x{}_d{}.flat", dim, dim, depth)
+}
+
+/// Load an automaton for boards of dimension `dim`, memory-mapping it if the
+/// file is in the flat layout
+pub fn load_automaton<P: AsRef<Path>>(
+    path: P,
+    dim: usize,
+) -> Result<Box<dyn Automaton>, AutomatonFileError> {
+    match FlatTrie::open(&path) {
+        Ok(flat) => {
+            if !flat.is_for(dim) {
+                return Err(AutomatonFileError::WrongSize {
+                    expected: dim,
+                    found: flat.dim,
+                    depth: flat.depth,
+                });
+            }
+            Ok(Box::new(flat))
+        }
+        Err(AutomatonFileError::BadMagic) => {
+            Ok(Box::new(PruningAutomaton::load_for(path, dim)?.trie))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Map the automaton for `dim` and `depth` from the user cache, building and
+/// caching it if needed
+pub fn load_or_build(dim: usize, depth: usize) -> Result<Box<dyn Automaton>, Box<dyn Error>> {
+    match cache_dir() {
+        Some(dir) => load_or_build_in(&dir, dim, depth),
+        None => Ok(Box::new(PruningAutomaton::build(dim, depth)?.trie)),
+    }
+}
+
+/// Same as `load_or_build` with the cache in `dir`
+pub fn load_or_build_in(
+    dir: &Path,
+    dim: usize,
+    depth: usize,
+) -> Result<Box<dyn Automaton>, Box<dyn Error>> {
+    if dim == 0 || depth == 0 {
+        return Err(From::from("can't build a prunning automaton of size or depth 0"));
+    }
+    let path = dir.join(file_name(dim, depth));
+    // stale or foreign files are rebuilt
+    if let Ok(flat) = FlatTrie::open(&path) {
+        if flat.is_for(dim) && flat.depth == depth {
+            return Ok(Box::new(flat));
+        }
+    }
+    let automaton = PruningAutomaton::build(dim, depth)?;
+    // renamed into place so that the processes mapping the old file keep it,
+    // and a cache we can't write to only costs a rebuild next time
+    let tmp = path.with_extension(format!("{}.tmp", ::std::process::id()));
+    let _ = fs::create_dir_all(dir)
+        .map_err(From::from)
+        .and_then(|_| FlatTrie::write(&automaton, &tmp))
+        .and_then(|_| fs::rename(&tmp, &path).map_err(From::from));
+    Ok(Box::new(automaton.trie))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar;
+    use construct_pruning_trie::construct_pruning_trie;
+    use heuristic::Manhattan;
+    use idastar;
+    use search_problem::TaquinProblem;
+    use taquin::Dir::*;
+    use taquin::Taquin;
+    use trie::TrieType;
+    #[test]
+    fn normalized() {
+        assert_eq!(
+            construct_pruning_trie(5, 3).unwrap().0,
+            construct_pruning_trie(4, 3).unwrap().0
+        );
+        assert_eq!(file_name(42, 10), file_name(11, 10));
+    }
+    fn is_redundant(automaton: &dyn Automaton, word: &[Dir]) -> bool {
+        word.iter()
+            .fold(TrieType::Match(0), |s, &d| automaton.change_true_state(&s, d))
+            == TrieType::Redundant
+    }
+    #[test]
+    fn cache() {
+        let dir = env::temp_dir().join(format!("npuzzle-test-{}", ::std::process::id()));
+        let built = load_or_build_in(&dir, 3, 6).unwrap();
+        assert!(dir.join(file_name(3, 6)).exists());
+        let loaded = load_or_build_in(&dir, 3, 6).unwrap();
+        for word in [vec![Right, Left], vec![Up, Right, Down, Left, Up, Right]].iter() {
+            assert!(is_redundant(&*built, word));
+            assert!(is_redundant(&*loaded, word));
+        }
+        assert!(!is_redundant(&*loaded, &[Right, Up, Left]));
+        assert!(load_automaton(dir.join(file_name(3, 6)), 3).is_ok());
+        match load_automaton(dir.join(file_name(3, 6)), 4) {
+            Err(AutomatonFileError::WrongSize { .. }) => (),
+            _ => panic!("wrong size accepted"),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(load_or_build_in(&dir, 0, 6).is_err());
+    }
+    #[test]
+    fn last_move() {
+        let spiral = Taquin::spiral(3);
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+        let (path, _) = idastar::search(&problem, &taquin).unwrap();
+        let (astar_path, _) = astar::search(&problem, &taquin).unwrap();
+        assert_eq!(path.len(), astar_path.len());
+    }
+    #[test]
+    fn file_format() {
+        let automaton = PruningAutomaton::build(3, 4).unwrap();
+        let mut bytes = automaton.to_bytes().unwrap();
+        assert_eq!(PruningAutomaton::from_bytes(&bytes).unwrap(), automaton);
+
+        match PruningAutomaton::from_bytes(&serialize(&automaton.trie).unwrap()) {
+            Err(AutomatonFileError::BadMagic) => (),
+            r => panic!("{:?}", r),
+        }
+        match PruningAutomaton::from_bytes(&bytes[..HEADER_LEN - 1]) {
+            Err(AutomatonFileError::Truncated) => (),
+            r => panic!("{:?}", r),
+        }
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        match PruningAutomaton::from_bytes(&bytes) {
+            Err(AutomatonFileError::BadChecksum) => (),
+            r => panic!("{:?}", r),
+        }
+        bytes[8] = 42;
+        match PruningAutomaton::from_bytes(&bytes) {
+            Err(AutomatonFileError::UnsupportedVersion(42)) => (),
+            r => panic!("{:?}", r),
+        }
+
+        let migrated = PruningAutomaton::load_for("prunning_automaton_3x3_d10.serde", 3).unwrap();
+        assert_eq!((migrated.dim, migrated.depth), (3, 10));
+        assert!(!migrated.is_for(4));
+        assert!(PruningAutomaton::build(42, 4).unwrap().is_for(5));
+    }
+}
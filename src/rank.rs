@@ -0,0 +1,135 @@
+//! Bijections between permutations and integers.
+//!
+//! The lehmer rank follows the lexicographic order of the permutations, the
+//! Myrvold-Ruskey rank has no meaningful order but is computed in linear time.
+//! Both fit in a u64 up to `MAX_RANK_LEN` elements, so up to the 4x4 boards.
+
+/// Longest permutation whose rank fits in a u64
+pub const MAX_RANK_LEN: usize = 20;
+
+/// Number of permutations of `len` elements
+pub fn nb_permutations(len: usize) -> u64 {
+    (1..len as u64 + 1).product()
+}
+
+/// Number of ways to place `k` distinct elements in `len` cells
+pub fn nb_partial_permutations(k: usize, len: usize) -> u64 {
+    (len as u64 - k as u64 + 1..len as u64 + 1).product()
+}
+
+/// Lexicographic rank of `perm`, a permutation of 0..perm.len(). Panics
+/// above `MAX_RANK_LEN` elements.
+pub fn lehmer_rank(perm: &[u64]) -> u64 {
+    assert!(perm.len() <= MAX_RANK_LEN, "permutation too long to be ranked");
+    let len = perm.len();
+    let mut rank = 0;
+    for (i, &p) in perm.iter().enumerate() {
+        let smaller_after = perm[i + 1..].iter().filter(|&&x| x < p).count() as u64;
+        rank += smaller_after * nb_permutations(len - 1 - i);
+    }
+    rank
+}
+
+pub fn lehmer_unrank(len: usize, mut rank: u64) -> Vec<u64> {
+    debug_assert!(rank < nb_permutations(len));
+    let mut remaining: Vec<u64> = (0..len as u64).collect();
+    let mut perm = Vec::with_capacity(len);
+    for i in 0..len {
+        let f = nb_permutations(len - 1 - i);
+        perm.push(remaining.remove((rank / f) as usize));
+        rank %= f;
+    }
+    perm
+}
+
+/// Linear time rank of `perm`, a permutation of 0..perm.len(). Panics above
+/// `MAX_RANK_LEN` elements.
+pub fn myrvold_ruskey_rank(perm: &[u64]) -> u64 {
+    assert!(perm.len() <= MAX_RANK_LEN, "permutation too long to be ranked");
+    let mut perm: Vec<usize> = perm.iter().map(|&p| p as usize).collect();
+    let mut inverse = vec![0; perm.len()];
+    for (i, &p) in perm.iter().enumerate() {
+        inverse[p] = i;
+    }
+    let mut rank = 0;
+    let mut mult = 1;
+    for k in (2..perm.len() + 1).rev() {
+        let s = perm[k - 1];
+        let last = inverse[k - 1];
+        perm.swap(k - 1, last);
+        inverse.swap(s, k - 1);
+        rank += s as u64 * mult;
+        mult *= k as u64;
+    }
+    rank
+}
+
+pub fn myrvold_ruskey_unrank(len: usize, mut rank: u64) -> Vec<u64> {
+    debug_assert!(rank < nb_permutations(len));
+    let mut perm: Vec<u64> = (0..len as u64).collect();
+    for k in (1..len + 1).rev() {
+        perm.swap(k - 1, (rank % k as u64) as usize);
+        rank /= k as u64;
+    }
+    perm
+}
+
+/// Lexicographic rank of `positions`, distinct cells among 0..len
+pub fn partial_rank(positions: &[usize], len: usize) -> u64 {
+    let k = positions.len();
+    let mut rank = 0;
+    for (i, &p) in positions.iter().enumerate() {
+        let taken_before = positions[..i].iter().filter(|&&x| x < p).count();
+        rank += (p - taken_before) as u64 * nb_partial_permutations(k - 1 - i, len - 1 - i);
+    }
+    rank
+}
+
+pub fn partial_unrank(k: usize, len: usize, mut rank: u64) -> Vec<usize> {
+    debug_assert!(rank < nb_partial_permutations(k, len));
+    let mut remaining: Vec<usize> = (0..len).collect();
+    let mut positions = Vec::with_capacity(k);
+    for i in 0..k {
+        let f = nb_partial_permutations(k - 1 - i, len - 1 - i);
+        positions.push(remaining.remove((rank / f) as usize));
+        rank %= f;
+    }
+    positions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+    #[test]
+    fn lehmer() {
+        assert_eq!(lehmer_rank(&[0, 1, 2, 3]), 0);
+        assert_eq!(lehmer_rank(&[3, 2, 1, 0]), nb_permutations(4) - 1);
+        for r in 0..nb_permutations(9) {
+            assert_eq!(lehmer_rank(&lehmer_unrank(9, r)), r);
+        }
+    }
+    #[test]
+    fn myrvold_ruskey() {
+        let mut seen = HashSet::new();
+        for r in 0..nb_permutations(9) {
+            let perm = myrvold_ruskey_unrank(9, r);
+            assert_eq!(myrvold_ruskey_rank(&perm), r);
+            assert!(seen.insert(perm));
+        }
+    }
+    #[test]
+    fn partial() {
+        assert_eq!(nb_partial_permutations(3, 9), 9 * 8 * 7);
+        let mut seen = HashSet::new();
+        for r in 0..nb_partial_permutations(3, 9) {
+            let positions = partial_unrank(3, 9, r);
+            assert_eq!(partial_rank(&positions, 9), r);
+            assert!(seen.insert(positions));
+        }
+        assert_eq!(partial_rank(&[0, 1, 2, 3, 4, 5, 6, 7, 8], 9), 0);
+        let perm = [4, 0, 8, 1, 2, 7, 3, 6, 5];
+        let positions: Vec<usize> = perm.iter().map(|&p| p as usize).collect();
+        assert_eq!(partial_rank(&positions, 9), lehmer_rank(&perm));
+    }
+}
@@ -0,0 +1,119 @@
+use complexity::Complexity;
+use num_traits::Zero;
+use search_problem::FCost::{self, Finite, Infinite};
+use search_problem::SearchProblem;
+
+enum Res<C> {
+    Found,
+    /// Best f-cost of the subtree, backed up to its parent
+    Failed(FCost<C>),
+    Interrupted,
+}
+
+use self::Res::*;
+
+fn aux<P: SearchProblem>(
+    problem: &P,
+    start: P::State,
+    path: &mut Vec<P::State>,
+    g_cost: P::Cost,
+    f_cost: FCost<P::Cost>,
+    limit: FCost<P::Cost>,
+    init_state: P::Pruning,
+    complexity: &mut Complexity,
+    depth: usize,
+) -> Res<P::Cost> {
+    if problem.interrupted() {
+        return Interrupted;
+    }
+    if let Finite(f_cost) = f_cost {
+        problem.progress(Some(f_cost), 0);
+    }
+    if problem.is_goal(&start) {
+        path.push(start);
+        return Found;
+    }
+    if depth > complexity.in_size {
+        complexity.in_size = depth;
+    }
+    complexity.in_time += 1;
+    let mut children = Vec::new();
+    for (a, c) in problem.successors(&start) {
+        let new_state = problem.next_pruning(&init_state, a);
+        if problem.is_pruned(&new_state) {
+            continue;
+        }
+        let n = problem.apply(&start, a);
+        // the f-cost of a child cannot be lower than a bound already found
+        let child_f = Finite(g_cost + c + problem.heuristic(&n)).max(f_cost);
+        children.push((child_f, n, g_cost + c, new_state));
+    }
+    loop {
+        children.sort_by_key(|child| child.0);
+        let best_f = match children.first() {
+            Some(&(Infinite, ..)) | None => return Failed(Infinite),
+            Some(&(f, ..)) if f > limit => return Failed(f),
+            Some(&(f, ..)) => f,
+        };
+        let alternative = children.get(1).map_or(Infinite, |child| child.0);
+        let (n, g, new_state) = (children[0].1.clone(), children[0].2, children[0].3);
+        match aux(
+            problem,
+            n,
+            path,
+            g,
+            best_f,
+            limit.min(alternative),
+            new_state,
+            complexity,
+            depth + 1,
+        ) {
+            Found => {
+                path.push(start);
+                return Found;
+            }
+            Failed(f) => children[0].0 = f,
+            Interrupted => return Interrupted,
+        }
+    }
+}
+
+/// Recursive best first search: a depth first search that remembers the
+/// f-cost of the best alternative at each level, and backs up the f-cost of
+/// the subtrees it gives up. Memory is linear in the depth of the solution,
+/// and fewer nodes are expanded again than by idastar.
+pub fn search<P: SearchProblem>(
+    problem: &P,
+    start: &P::State,
+) -> Option<(Vec<P::State>, Complexity)> {
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 0,
+    };
+    let mut path = Vec::new();
+    match aux(
+        problem,
+        start.clone(),
+        &mut path,
+        P::Cost::zero(),
+        Finite(problem.heuristic(start)),
+        Infinite,
+        problem.initial_pruning(),
+        &mut complexity,
+        1,
+    ) {
+        Found => Some((path, complexity)),
+        Failed(_) | Interrupted => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use search_problem::test::assert_optimal_like_astar;
+
+    #[test]
+    fn optimal() {
+        assert_optimal_like_astar(|problem, taquin| search(problem, taquin).map(|(path, _)| path));
+    }
+}
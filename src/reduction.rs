@@ -0,0 +1,364 @@
+use astar;
+use heuristic::Manhattan;
+use search_problem::TaquinProblem;
+use std::collections::VecDeque;
+use taquin::{Dir, Taquin};
+
+/// Side of the square left to astar once the rest of the board is solved
+pub const FINISH_DIM: usize = 3;
+
+const DIRS: [Dir; 4] = [Dir::Right, Dir::Up, Dir::Down, Dir::Left];
+
+/// Moves solving `taquin` the way a human does: the rows and columns on the
+/// border are placed tile by tile and locked, shrinking the board until only
+/// a 3x3 square around the blank of the goal is left, which astar finishes.
+/// Works toward any goal, the spiral one peels its outer ring this way.
+///
+/// Time is polynomial in the size of the board but the solution is far from
+/// optimal. None if `taquin` cannot reach `goal`.
+pub fn solve(taquin: &Taquin, goal: &Taquin) -> Option<Vec<Dir>> {
+    let n = taquin.dim();
+    if goal.dim() != n || !taquin.is_solvable(goal) {
+        return None;
+    }
+    let goal: Vec<u64> = goal.iter().cloned().collect();
+    let goal_blank = goal.iter().position(|&t| t == 0).unwrap();
+    let (blank_row, blank_col) = (goal_blank / n, goal_blank % n);
+    let mut board = Board::new(taquin);
+    // rows r0..r1 and columns c0..c1 are not solved yet
+    let (mut r0, mut r1, mut c0, mut c1) = (0, n, 0, n);
+    while r1 - r0 > FINISH_DIM || c1 - c0 > FINISH_DIM {
+        if r1 - r0 >= c1 - c0 {
+            let (row, inward) = if blank_row != r0 {
+                r0 += 1;
+                (r0 - 1, Dir::Down)
+            } else {
+                r1 -= 1;
+                (r1, Dir::Up)
+            };
+            let line: Vec<usize> = (c0..c1).map(|col| row * n + col).collect();
+            board.solve_line(&line, inward, &goal)?;
+        } else {
+            let (col, inward) = if blank_col != c0 {
+                c0 += 1;
+                (c0 - 1, Dir::Right)
+            } else {
+                c1 -= 1;
+                (c1, Dir::Left)
+            };
+            let line: Vec<usize> = (r0..r1).map(|row| row * n + col).collect();
+            board.solve_line(&line, inward, &goal)?;
+        }
+    }
+    board.finish(r0 * n + c0, r1 - r0, &goal)?;
+    Some(board.moves)
+}
+
+/// Board mutated in place, since copying a 50x50 board at every move would
+/// cost more than the whole search
+struct Board {
+    n: usize,
+    cells: Vec<u64>,
+    /// Cell of every tile, the blank first
+    positions: Vec<usize>,
+    /// Solved cells that must not move anymore
+    locked: Vec<bool>,
+    moves: Vec<Dir>,
+    /// Breadth first search marks, a cell is seen if it holds `generation`
+    seen: Vec<u32>,
+    generation: u32,
+    from: Vec<usize>,
+}
+
+impl Board {
+    fn new(taquin: &Taquin) -> Self {
+        let cells: Vec<u64> = taquin.iter().cloned().collect();
+        let mut positions = vec![0; cells.len()];
+        for (cell, &tile) in cells.iter().enumerate() {
+            positions[tile as usize] = cell;
+        }
+        Board {
+            n: taquin.dim(),
+            locked: vec![false; cells.len()],
+            seen: vec![0; cells.len()],
+            generation: 0,
+            from: vec![0; cells.len()],
+            moves: Vec::new(),
+            positions,
+            cells,
+        }
+    }
+
+    fn blank(&self) -> usize {
+        self.positions[0]
+    }
+
+    fn neighbour(&self, cell: usize, dir: Dir) -> Option<usize> {
+        let (row, col) = (cell / self.n, cell % self.n);
+        match dir {
+            Dir::Right if col + 1 < self.n => Some(cell + 1),
+            Dir::Up if row > 0 => Some(cell - self.n),
+            Dir::Down if row + 1 < self.n => Some(cell + self.n),
+            Dir::Left if col > 0 => Some(cell - 1),
+            _ => None,
+        }
+    }
+
+    /// Direction from `cell` to the adjacent cell `to`
+    fn dir(&self, cell: usize, to: usize) -> Dir {
+        *DIRS
+            .iter()
+            .find(|&&d| self.neighbour(cell, d) == Some(to))
+            .expect("cells are not adjacent")
+    }
+
+    fn move_blank(&mut self, dir: Dir) {
+        let blank = self.blank();
+        let cell = self.neighbour(blank, dir).unwrap();
+        let tile = self.cells[cell];
+        self.cells.swap(blank, cell);
+        self.positions[tile as usize] = blank;
+        self.positions[0] = cell;
+        self.moves.push(dir);
+    }
+
+    /// Shortest path from `start` to `target`, start excluded, through cells
+    /// neither locked nor in `avoid`
+    fn path(&mut self, start: usize, target: usize, avoid: &[usize]) -> Option<Vec<usize>> {
+        self.generation += 1;
+        let generation = self.generation;
+        let mut queue = VecDeque::new();
+        self.seen[start] = generation;
+        queue.push_back(start);
+        while let Some(cell) = queue.pop_front() {
+            if cell == target {
+                let mut path = Vec::new();
+                let mut cell = target;
+                while cell != start {
+                    path.push(cell);
+                    cell = self.from[cell];
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &dir in DIRS.iter() {
+                if let Some(next) = self.neighbour(cell, dir) {
+                    if self.seen[next] != generation && !self.locked[next] && !avoid.contains(&next)
+                    {
+                        self.seen[next] = generation;
+                        self.from[next] = cell;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn move_blank_to(&mut self, target: usize, avoid: &[usize]) -> Option<()> {
+        let blank = self.blank();
+        let mut cell = blank;
+        for next in self.path(blank, target, avoid)? {
+            let dir = self.dir(cell, next);
+            self.move_blank(dir);
+            cell = next;
+        }
+        Some(())
+    }
+
+    /// Push `tile` to `target` one cell at a time, bringing the blank in
+    /// front of it without going through the tile. The tile never goes
+    /// through `avoid`.
+    fn move_tile(&mut self, tile: u64, target: usize, avoid: &[usize]) -> Option<()> {
+        let start = self.positions[tile as usize];
+        for next in self.path(start, target, avoid)? {
+            let cell = self.positions[tile as usize];
+            self.move_blank_to(next, &[cell])?;
+            let dir = self.dir(next, cell);
+            self.move_blank(dir);
+        }
+        Some(())
+    }
+
+    /// Place the tiles of `line`, a border of the unsolved rectangle at
+    /// least 3 cells long with at least 2 unsolved lines behind it in the
+    /// `inward` direction, and lock them
+    fn solve_line(&mut self, line: &[usize], inward: Dir, goal: &[u64]) -> Option<()> {
+        let len = line.len();
+        for &cell in line[..len - 2].iter() {
+            self.move_tile(goal[cell], cell, &[])?;
+            self.locked[cell] = true;
+        }
+        // the last two tiles cannot be pushed in one after the other
+        let (cell_a, cell_b) = (line[len - 2], line[len - 1]);
+        let (a, b) = (goal[cell_a], goal[cell_b]);
+        if self.cells[cell_a] != a || self.cells[cell_b] != b {
+            let mut window = Vec::new();
+            for &cell in line[len - 3..].iter() {
+                let mut cell = cell;
+                window.push(cell);
+                for _ in 0..2 {
+                    cell = self.neighbour(cell, inward).unwrap();
+                    window.push(cell);
+                }
+            }
+            window.retain(|&cell| cell != line[len - 3]);
+            // gather a, b and the blank in the 3x3 corner, then shuffle them
+            // into place there
+            self.move_tile(a, cell_b, &[])?;
+            if !window.contains(&self.positions[b as usize]) {
+                // a is locked in the corner, b must not enter the dead end
+                // next to it: the blank would be stuck there behind b
+                self.locked[cell_b] = true;
+                let parking = window[window.len() - 1];
+                let moved = self.move_tile(b, parking, &[cell_a]);
+                self.locked[cell_b] = false;
+                moved?;
+            }
+            if !window.contains(&self.blank()) {
+                let avoid = [self.positions[a as usize], self.positions[b as usize]];
+                let target = window.iter().find(|cell| !avoid.contains(cell)).cloned();
+                self.move_blank_to(target.unwrap(), &avoid)?;
+            }
+            self.solve_window(&window, (a, cell_a), (b, cell_b))?;
+        }
+        self.locked[cell_a] = true;
+        self.locked[cell_b] = true;
+        Some(())
+    }
+
+    /// Breadth first search over the positions of `a`, `b` and the blank
+    /// when the blank stays in `window`, the other tiles being
+    /// indistinguishable
+    fn solve_window(&mut self, window: &[usize], a: (u64, usize), b: (u64, usize)) -> Option<()> {
+        let w = window.len();
+        let index = |cell: usize| window.iter().position(|&c| c == cell).unwrap();
+        let encode = |a: usize, b: usize, blank: usize| (a * w + b) * w + blank;
+        let start = encode(
+            index(self.positions[a.0 as usize]),
+            index(self.positions[b.0 as usize]),
+            index(self.blank()),
+        );
+        let goal = (index(a.1), index(b.1));
+        let mut from: Vec<Option<(usize, Dir)>> = vec![None; w * w * w];
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        let mut found = None;
+        while let Some(state) = queue.pop_front() {
+            let (pa, pb, blank) = (state / (w * w), state / w % w, state % w);
+            if (pa, pb) == goal {
+                found = Some(state);
+                break;
+            }
+            for &dir in DIRS.iter() {
+                let next = match self.neighbour(window[blank], dir) {
+                    Some(cell) if window.contains(&cell) => index(cell),
+                    _ => continue,
+                };
+                let (na, nb) = match next {
+                    _ if next == pa => (blank, pb),
+                    _ if next == pb => (pa, blank),
+                    _ => (pa, pb),
+                };
+                let next = encode(na, nb, next);
+                if next != start && from[next].is_none() {
+                    from[next] = Some((state, dir));
+                    queue.push_back(next);
+                }
+            }
+        }
+        let mut state = found?;
+        let mut dirs = Vec::new();
+        while let Some((previous, dir)) = from[state] {
+            dirs.push(dir);
+            state = previous;
+        }
+        for &dir in dirs.iter().rev() {
+            self.move_blank(dir);
+        }
+        Some(())
+    }
+
+    /// Solve the `dim`x`dim` square whose top left cell is `corner` with
+    /// astar, the rest of the board being solved
+    fn finish(&mut self, corner: usize, dim: usize, goal: &[u64]) -> Option<()> {
+        let cells: Vec<usize> = (0..dim * dim)
+            .map(|i| corner + i / dim * self.n + i % dim)
+            .collect();
+        // tiles are renamed after their goal cell in the square
+        let mut names = vec![0; goal.len()];
+        let mut next_name = 1;
+        for &cell in cells.iter() {
+            if goal[cell] != 0 {
+                names[goal[cell] as usize] = next_name;
+                next_name += 1;
+            }
+        }
+        let square =
+            |tiles: Vec<u64>| Taquin::new(dim, tiles.iter().map(|&t| names[t as usize]).collect());
+        let start = square(cells.iter().map(|&cell| self.cells[cell]).collect());
+        let goal = square(cells.iter().map(|&cell| goal[cell]).collect());
+        let (path, _) = astar::search(&TaquinProblem::new(goal, &Manhattan), &start)?;
+        for (current, next) in path.iter().rev().zip(path.iter().rev().skip(1)) {
+            self.move_blank(current.dir_to(next)?);
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row_major(n: usize) -> Taquin {
+        Taquin::new(n, (1..n * n).map(|t| t as u64).chain(Some(0)).collect())
+    }
+
+    fn check(taquin: &Taquin, goal: &Taquin) {
+        let moves = solve(taquin, goal).unwrap();
+        let solved = moves
+            .iter()
+            .fold(taquin.clone(), |t, &dir| t.move_piece(dir).unwrap());
+        assert!(solved.is_solved(goal));
+    }
+
+    fn random_solvable(goal: &Taquin) -> Taquin {
+        let mut taquin = Taquin::new_random(goal.dim());
+        if !taquin.is_solvable(goal) {
+            // swapping two tiles changes the parity
+            let mut pieces: Vec<u64> = taquin.iter().cloned().collect();
+            let (i, j) = match pieces.iter().position(|&t| t == 0).unwrap() {
+                0 | 1 => (2, 3),
+                _ => (0, 1),
+            };
+            pieces.swap(i, j);
+            taquin = Taquin::new(goal.dim(), pieces);
+        }
+        taquin
+    }
+
+    #[test]
+    fn goals() {
+        for n in 1..12 {
+            for goal in [Taquin::spiral(n), row_major(n)].iter() {
+                for _ in 0..3 {
+                    check(&random_solvable(goal), goal);
+                }
+                check(goal, goal);
+            }
+        }
+    }
+    #[test]
+    fn big() {
+        let goal = Taquin::spiral(30);
+        check(&random_solvable(&goal), &goal);
+        for goal in [Taquin::spiral(50), row_major(50)].iter() {
+            check(&random_solvable(goal), goal);
+        }
+    }
+    #[test]
+    fn unsolvable() {
+        let taquin = Taquin::new(3, vec![2, 1, 3, 8, 0, 4, 7, 6, 5]);
+        assert_eq!(solve(&taquin, &Taquin::spiral(3)), None);
+    }
+}
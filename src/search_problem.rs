@@ -0,0 +1,233 @@
+use heuristic::Heuristic;
+use num_traits::Zero;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use taquin::{Dir, Taquin};
+use trie::{Automaton, TrieType};
+
+/// Everything a search algorithm needs to know about the problem it solves.
+///
+/// `Pruning` is the state of an optional pruning automaton, carried along the
+/// current path by the algorithms that can use it (idastar). Problems without
+/// one can use `()` and never prune.
+pub trait SearchProblem {
+    type State: Clone;
+    type Action: Copy;
+    type Cost: Zero + Ord + Copy + Debug;
+    type Pruning: Copy;
+
+    /// Actions available from `state` with their cost
+    fn successors(&self, state: &Self::State) -> Vec<(Self::Action, Self::Cost)>;
+
+    /// State reached by performing `action` on `state`
+    fn apply(&self, state: &Self::State, action: Self::Action) -> Self::State;
+
+    fn heuristic(&self, state: &Self::State) -> Self::Cost;
+
+    fn is_goal(&self, state: &Self::State) -> bool;
+
+    /// Pruning state of the empty path
+    fn initial_pruning(&self) -> Self::Pruning;
+
+    /// Pruning state after appending `action` to the path
+    fn next_pruning(&self, pruning: &Self::Pruning, action: Self::Action) -> Self::Pruning;
+
+    fn is_pruned(&self, pruning: &Self::Pruning) -> bool;
+
+    /// Polled by the algorithms once per expansion, they give up as soon as it
+    /// returns true
+    fn interrupted(&self) -> bool {
+        false
+    }
+
+    /// Told by the algorithms of the f-cost bound they search within, if they
+    /// have one, and of their number of open states, 0 for the depth-first
+    /// ones. Called often, it must be cheap.
+    fn progress(&self, _bound: Option<Self::Cost>, _open: usize) {}
+}
+
+/// f-cost backed up by the memory-bounded algorithms, infinite when no
+/// solution is left below a node
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FCost<C> {
+    Finite(C),
+    Infinite,
+}
+
+/// Adapter turning the historical closure arguments into a `SearchProblem`.
+#[derive(new)]
+pub struct Closures<N, A, C, FN, FA, FH, FS, S, CS, IR> {
+    neighbours_actions: FN,
+    perform_action: FA,
+    heuristic: FH,
+    success: FS,
+    init_state: S,
+    change_state: CS,
+    is_redundant: IR,
+    marker: PhantomData<fn(&N, A) -> C>,
+}
+
+/// `change_state` of closure problems without a pruning automaton
+pub fn no_pruning<A>(_: &(), _: A) {}
+
+/// `is_redundant` of closure problems without a pruning automaton
+pub fn never_redundant(_: &()) -> bool {
+    false
+}
+
+impl<N, A, C, FN, IN, FA, FH, FS, S, CS, IR> SearchProblem
+    for Closures<N, A, C, FN, FA, FH, FS, S, CS, IR>
+where
+    N: Clone,
+    C: Zero + Ord + Copy + Debug,
+    FN: Fn(&N) -> IN,
+    IN: IntoIterator<Item = (A, C)>,
+    FA: Fn(&N, A) -> N,
+    A: Copy,
+    FH: Fn(&N) -> C,
+    FS: Fn(&N) -> bool,
+    S: Copy,
+    CS: Fn(&S, A) -> S,
+    IR: Fn(&S) -> bool,
+{
+    type State = N;
+    type Action = A;
+    type Cost = C;
+    type Pruning = S;
+
+    fn successors(&self, state: &N) -> Vec<(A, C)> {
+        (self.neighbours_actions)(state).into_iter().collect()
+    }
+    fn apply(&self, state: &N, action: A) -> N {
+        (self.perform_action)(state, action)
+    }
+    fn heuristic(&self, state: &N) -> C {
+        (self.heuristic)(state)
+    }
+    fn is_goal(&self, state: &N) -> bool {
+        (self.success)(state)
+    }
+    fn initial_pruning(&self) -> S {
+        self.init_state
+    }
+    fn next_pruning(&self, pruning: &S, action: A) -> S {
+        (self.change_state)(pruning, action)
+    }
+    fn is_pruned(&self, pruning: &S) -> bool {
+        (self.is_redundant)(pruning)
+    }
+}
+
+/// Pruning state of a `TaquinProblem`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TaquinPruning {
+    /// State in the pruning automaton
+    Automaton(TrieType),
+    /// Without automaton only the moves undoing the last one are pruned
+    LastMove(Option<Dir>),
+    Undo,
+}
+
+/// Solving a `Taquin` toward `goal`, each move costing 1.
+pub struct TaquinProblem<'a> {
+    goal: Taquin,
+    heuristic: &'a dyn Heuristic,
+    automaton: Option<&'a dyn Automaton>,
+}
+
+impl<'a> TaquinProblem<'a> {
+    pub fn new(goal: Taquin, heuristic: &'a dyn Heuristic) -> Self {
+        TaquinProblem {
+            goal,
+            heuristic,
+            automaton: None,
+        }
+    }
+
+    /// Prune the move sequences recognized as redundant by `automaton`
+    pub fn with_automaton(mut self, automaton: &'a dyn Automaton) -> Self {
+        self.automaton = Some(automaton);
+        self
+    }
+
+    pub fn goal(&self) -> &Taquin {
+        &self.goal
+    }
+}
+
+impl<'a> SearchProblem for TaquinProblem<'a> {
+    type State = Taquin;
+    type Action = Dir;
+    type Cost = u64;
+    type Pruning = TaquinPruning;
+
+    fn successors(&self, state: &Taquin) -> Vec<(Dir, u64)> {
+        state.neighbours().into_iter().map(|d| (d, 1)).collect()
+    }
+    fn apply(&self, state: &Taquin, action: Dir) -> Taquin {
+        state.move_piece(action).unwrap()
+    }
+    fn heuristic(&self, state: &Taquin) -> u64 {
+        self.heuristic.evaluate(state, &self.goal)
+    }
+    fn is_goal(&self, state: &Taquin) -> bool {
+        state.is_solved(&self.goal)
+    }
+    fn initial_pruning(&self) -> TaquinPruning {
+        match self.automaton {
+            Some(_) => TaquinPruning::Automaton(TrieType::Match(0)),
+            None => TaquinPruning::LastMove(None),
+        }
+    }
+    fn next_pruning(&self, pruning: &TaquinPruning, action: Dir) -> TaquinPruning {
+        match (*pruning, self.automaton) {
+            (TaquinPruning::Automaton(state), Some(automaton)) => {
+                TaquinPruning::Automaton(automaton.change_true_state(&state, action))
+            }
+            (TaquinPruning::LastMove(Some(last)), _) if action == last.oposite() => {
+                TaquinPruning::Undo
+            }
+            _ => TaquinPruning::LastMove(Some(action)),
+        }
+    }
+    fn is_pruned(&self, pruning: &TaquinPruning) -> bool {
+        match *pruning {
+            TaquinPruning::Automaton(TrieType::Redundant) | TaquinPruning::Undo => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use astar;
+    use heuristic::Manhattan;
+
+    /// Check on a few 3x3 boards that `search` finds paths as short as astar,
+    /// from the spiral to the board like the algorithms return them. `search`
+    /// is given the problem toward the spiral with the manhattan heuristic.
+    pub fn assert_optimal_like_astar<F>(search: F)
+    where
+        F: Fn(&TaquinProblem, &Taquin) -> Option<Vec<Taquin>>,
+    {
+        let spiral = Taquin::spiral(3);
+        let problem = TaquinProblem::new(spiral.clone(), &Manhattan);
+        for pieces in [
+            vec![5, 1, 0, 8, 4, 6, 3, 7, 2],
+            vec![8, 1, 3, 0, 7, 2, 6, 5, 4],
+            vec![0, 1, 2, 6, 4, 3, 5, 8, 7],
+            vec![1, 2, 3, 8, 0, 4, 7, 6, 5],
+        ]
+        .iter()
+        {
+            let taquin = Taquin::new(3, pieces.clone());
+            let path = search(&problem, &taquin).unwrap();
+            let (optimal, _) = astar::search(&problem, &taquin).unwrap();
+            assert_eq!(path.len(), optimal.len());
+            assert_eq!(path.first(), Some(&spiral));
+            assert_eq!(path.last(), Some(&taquin));
+            assert!(path.windows(2).all(|w| w[0].dir_to(&w[1]).is_some()));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use complexity::Complexity;
+use num_traits::Zero;
+use search_problem::FCost::{self, Finite, Infinite};
+use search_problem::SearchProblem;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+/// Nodes kept in memory when the caller sets no cap
+pub const DEFAULT_MAX_NODES: usize = 0x10_0000;
+
+struct Node<S, C, R> {
+    state: S,
+    parent: Option<usize>,
+    g_cost: C,
+    f_cost: FCost<C>,
+    depth: usize,
+    pruning: R,
+    /// Rank of the node among the successors of its parent
+    slot: usize,
+    /// Successors in memory
+    children: Vec<usize>,
+    /// Rank and f-cost of the successors removed from memory
+    forgotten: Vec<(usize, FCost<C>)>,
+}
+
+impl<S, C: Ord + Copy, R> Node<S, C, R> {
+    fn best_forgotten(&self) -> FCost<C> {
+        self.forgotten.iter().map(|f| f.1).min().unwrap_or(Infinite)
+    }
+}
+
+/// Lowest f-cost first, the deepest first among equals
+type Key<C> = (FCost<C>, Reverse<usize>, usize);
+
+/// Search tree held in at most `max_nodes` nodes
+struct Tree<S, C: Ord, R> {
+    nodes: Vec<Option<Node<S, C, R>>>,
+    free: Vec<usize>,
+    len: usize,
+    /// Nodes with successors to generate
+    open_set: BTreeSet<Key<C>>,
+}
+
+impl<S, C: Ord + Copy, R> Tree<S, C, R> {
+    fn node(&self, id: usize) -> &Node<S, C, R> {
+        self.nodes[id].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, id: usize) -> &mut Node<S, C, R> {
+        self.nodes[id].as_mut().unwrap()
+    }
+
+    fn key(&self, id: usize) -> Key<C> {
+        let node = self.node(id);
+        (node.f_cost, Reverse(node.depth), id)
+    }
+
+    fn insert(&mut self, node: Node<S, C, R>) -> usize {
+        self.len += 1;
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Best f-cost below the expanded node `id` goes up to its ancestors
+    fn backup(&mut self, mut id: usize) {
+        loop {
+            let best = {
+                let node = self.node(id);
+                let best = node
+                    .children
+                    .iter()
+                    .map(|&child| self.node(child).f_cost)
+                    .fold(node.best_forgotten(), FCost::min);
+                best.max(node.f_cost)
+            };
+            if best == self.node(id).f_cost {
+                return;
+            }
+            let is_open = self.open_set.remove(&self.key(id));
+            self.node_mut(id).f_cost = best;
+            if is_open {
+                let key = self.key(id);
+                self.open_set.insert(key);
+            }
+            match self.node(id).parent {
+                Some(parent) => id = parent,
+                None => return,
+            }
+        }
+    }
+
+    /// Remove the leaf `id`, its parent remembers its f-cost to generate it
+    /// again if it becomes the best. Dead ends are removed up to the root.
+    fn forget(&mut self, id: usize) {
+        self.open_set.remove(&self.key(id));
+        let node = self.nodes[id].take().unwrap();
+        self.free.push(id);
+        self.len -= 1;
+        let parent = node.parent.unwrap();
+        let was_open = self.open_set.remove(&self.key(parent));
+        {
+            let parent = self.node_mut(parent);
+            parent.children.retain(|&child| child != id);
+            parent.forgotten.push((node.slot, node.f_cost));
+        }
+        if self.node(parent).best_forgotten() != Infinite || was_open {
+            let key = self.key(parent);
+            self.open_set.insert(key);
+        }
+        let dead = {
+            let parent = self.node(parent);
+            parent.children.is_empty() && parent.best_forgotten() == Infinite
+        };
+        if dead && self.node(parent).parent.is_some() {
+            self.forget(parent);
+        } else {
+            self.backup(parent);
+        }
+    }
+}
+
+/// Simplified memory-bounded astar: astar as long as `max_nodes` nodes fit
+/// in memory, then the leaf of highest f-cost is forgotten, its parent
+/// keeping that f-cost to generate it again if everything else turns out
+/// worse. Optimal if a shortest path fits in memory, but it can thrash on
+/// too small a cap.
+pub fn search<P>(
+    problem: &P,
+    start: &P::State,
+    max_nodes: usize,
+) -> Option<(Vec<P::State>, Complexity)>
+where
+    P: SearchProblem,
+{
+    let mut complexity = Complexity {
+        in_time: 0,
+        in_size: 1,
+    };
+    let mut tree = Tree {
+        nodes: Vec::new(),
+        free: Vec::new(),
+        len: 0,
+        open_set: BTreeSet::new(),
+    };
+    let root = tree.insert(Node {
+        state: start.clone(),
+        parent: None,
+        g_cost: P::Cost::zero(),
+        f_cost: Finite(problem.heuristic(start)),
+        depth: 0,
+        pruning: problem.initial_pruning(),
+        slot: 0,
+        children: Vec::new(),
+        forgotten: Vec::new(),
+    });
+    let key = tree.key(root);
+    tree.open_set.insert(key);
+
+    loop {
+        let key = *tree.open_set.iter().next()?;
+        if key.0 == Infinite {
+            return None;
+        }
+        let id = key.2;
+        if problem.is_goal(&tree.node(id).state) {
+            let mut path = Vec::new();
+            let mut id = Some(id);
+            while let Some(i) = id {
+                path.push(tree.node(i).state.clone());
+                id = tree.node(i).parent;
+            }
+            return Some((path, complexity));
+        }
+        if problem.interrupted() {
+            return None;
+        }
+        if let Finite(f_cost) = key.0 {
+            problem.progress(Some(f_cost), tree.open_set.len());
+        }
+        complexity.in_time += 1;
+        tree.open_set.remove(&key);
+
+        // generate every successor the first time, then only the forgotten
+        // ones, which keep the f-cost backed up before
+        let mut successors = Vec::new();
+        {
+            let node = tree.node(id);
+            let first_time = node.children.is_empty() && node.forgotten.is_empty();
+            for (slot, (a, c)) in problem.successors(&node.state).into_iter().enumerate() {
+                let backed_up = node.forgotten.iter().find(|f| f.0 == slot).map(|f| f.1);
+                // dead ends forgotten stay forgotten
+                if !first_time && backed_up.map_or(true, |f| f == Infinite) {
+                    continue;
+                }
+                let pruning = problem.next_pruning(&node.pruning, a);
+                if problem.is_pruned(&pruning) {
+                    continue;
+                }
+                let state = problem.apply(&node.state, a);
+                let g_cost = node.g_cost + c;
+                // a longer path would not fit in memory
+                let f_cost = if node.depth + 2 >= max_nodes && !problem.is_goal(&state) {
+                    Infinite
+                } else {
+                    Finite(g_cost + problem.heuristic(&state))
+                        .max(node.f_cost)
+                        .max(backed_up.unwrap_or(node.f_cost))
+                };
+                successors.push(Node {
+                    state,
+                    parent: Some(id),
+                    g_cost,
+                    f_cost,
+                    depth: node.depth + 1,
+                    pruning,
+                    slot,
+                    children: Vec::new(),
+                    forgotten: Vec::new(),
+                });
+            }
+        }
+        for successor in successors {
+            let child = tree.insert(successor);
+            tree.node_mut(id).children.push(child);
+            let key = tree.key(child);
+            tree.open_set.insert(key);
+        }
+        tree.node_mut(id).forgotten.clear();
+        if tree.node(id).children.is_empty() && id != root {
+            tree.forget(id);
+        } else {
+            tree.backup(id);
+        }
+        complexity.in_size = complexity.in_size.max(tree.len);
+
+        while tree.len > max_nodes {
+            let worst = tree
+                .open_set
+                .iter()
+                .rev()
+                .find(|key| key.2 != root && tree.node(key.2).children.is_empty())
+                .cloned();
+            match worst {
+                Some(key) => tree.forget(key.2),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use heuristic::Manhattan;
+    use search_problem::test::assert_optimal_like_astar;
+    use search_problem::TaquinProblem;
+    use taquin::Taquin;
+
+    #[test]
+    fn optimal() {
+        for &max_nodes in [DEFAULT_MAX_NODES, 100].iter() {
+            assert_optimal_like_astar(|problem, taquin| {
+                let (path, complexity) = search(problem, taquin, max_nodes)?;
+                assert!(complexity.in_size <= max_nodes + 4);
+                Some(path)
+            });
+        }
+    }
+    #[test]
+    fn too_small() {
+        // the 18 moves do not fit in 10 nodes
+        let taquin = Taquin::new(3, vec![0, 1, 2, 6, 4, 3, 5, 8, 7]);
+        let problem = TaquinProblem::new(Taquin::spiral(3), &Manhattan);
+        assert!(search(&problem, &taquin, 10).is_none());
+    }
+}
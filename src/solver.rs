@@ -0,0 +1,841 @@
+use astar;
+use beam_search::{self, Beam};
+use breadth_first;
+use complexity::Complexity;
+use distance_table::DistanceTable;
+use depth_first;
+use dijkstra;
+use frontier_astar;
+use greedy_search::{self, TieBreak};
+use heuristic::{Heuristic, Manhattan, Zero};
+use idastar::{self, AllSolutions};
+use pruning;
+use rbfs;
+use reduction;
+use search_problem::{SearchProblem, TaquinProblem};
+use sma_star;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use taquin::{Dir, Taquin};
+use trie::Automaton;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Algorithm {
+    AStar,
+    /// astar keeping only the open states
+    FrontierAStar,
+    IdaStar,
+    /// Recursive best first search
+    Rbfs,
+    /// Simplified memory-bounded astar
+    SmaStar,
+    /// Dijkstra's algorithm
+    UniformCost,
+    BreadthFirst,
+    GreedySearch,
+    /// Depth first walk following the lowest heuristic first
+    DepthFirst,
+    /// Row and column reduction, polynomial but not optimal
+    Reduction,
+    /// Breadth first keeping the best states of each layer, not optimal
+    Beam,
+}
+
+impl Algorithm {
+    /// Whether the algorithm prunes with an automaton, the graph searches
+    /// detect the duplicates instead
+    pub fn uses_automaton(self) -> bool {
+        matches!(
+            self,
+            Algorithm::IdaStar | Algorithm::Rbfs | Algorithm::SmaStar | Algorithm::Beam
+        )
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = SolveError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "astar" => Ok(Algorithm::AStar),
+            "frontier_astar" => Ok(Algorithm::FrontierAStar),
+            "idastar" => Ok(Algorithm::IdaStar),
+            "rbfs" => Ok(Algorithm::Rbfs),
+            "sma_star" => Ok(Algorithm::SmaStar),
+            "uniform_cost" | "dijkstra" => Ok(Algorithm::UniformCost),
+            "breadth_first" => Ok(Algorithm::BreadthFirst),
+            "greedy_search" => Ok(Algorithm::GreedySearch),
+            "depth_first" => Ok(Algorithm::DepthFirst),
+            "reduction" => Ok(Algorithm::Reduction),
+            "beam" => Ok(Algorithm::Beam),
+            _ => Err(SolveError::UnknownAlgorithm(s.to_string())),
+        }
+    }
+}
+
+/// A solved taquin
+#[derive(Debug)]
+pub struct Solution {
+    /// Every state from the start to the goal
+    pub path: Vec<Taquin>,
+    pub complexity: Complexity,
+}
+
+/// Statistics given to the observer of a search while it runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress<C> {
+    /// Nodes expanded so far
+    pub expanded: usize,
+    /// f-cost bound of the search, the threshold of idastar
+    pub bound: Option<C>,
+    /// Open states, 0 for the depth-first algorithms
+    pub open: usize,
+    pub elapsed: Duration,
+}
+
+/// Told of the progress of a search, at most once per interval
+type Observer<'a, C> = (&'a (dyn Fn(&Progress<C>) + Sync), Duration);
+
+#[derive(Debug, PartialEq)]
+pub enum SolveError {
+    UnknownAlgorithm(String),
+    DimensionMismatch(usize, usize),
+    Unsolvable,
+    NodeLimit(usize),
+    TimeLimit(Duration),
+    NoSolution,
+    /// The idastar checkpoint could not be read or written
+    Checkpoint(String),
+    /// The heuristic named so could overestimate, the paths found would not
+    /// all be optimal
+    InadmissibleHeuristic(String),
+    /// `all_optimal` was asked for at most 0 solutions
+    NoSolutionAsked,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SolveError::UnknownAlgorithm(ref s) => write!(f, "unknown algorithm: {}", s),
+            SolveError::DimensionMismatch(taquin, goal) => write!(
+                f,
+                "the taquin is {}x{} but the goal is {}x{}",
+                taquin, taquin, goal, goal
+            ),
+            SolveError::Unsolvable => write!(f, "this is unsolvable"),
+            SolveError::NodeLimit(n) => write!(f, "gave up after expanding {} nodes", n),
+            SolveError::TimeLimit(d) => write!(
+                f,
+                "gave up after {} secondes and {} milisecondes",
+                d.as_secs(),
+                d.subsec_millis()
+            ),
+            SolveError::NoSolution => write!(f, "no solution found"),
+            SolveError::Checkpoint(ref e) => write!(f, "checkpoint: {}", e),
+            SolveError::InadmissibleHeuristic(ref name) => write!(
+                f,
+                "the {} heuristic is not admissible, the solutions would not be optimal",
+                name
+            ),
+            SolveError::NoSolutionAsked => write!(f, "at least one solution must be asked for"),
+        }
+    }
+}
+
+impl Error for SolveError {
+    fn description(&self) -> &str {
+        match *self {
+            SolveError::UnknownAlgorithm(_) => "unknown algorithm",
+            SolveError::DimensionMismatch(_, _) => "dimension mismatch",
+            SolveError::Unsolvable => "this is unsolvable",
+            SolveError::NodeLimit(_) => "node limit reached",
+            SolveError::TimeLimit(_) => "time limit reached",
+            SolveError::NoSolution => "no solution found",
+            SolveError::Checkpoint(_) => "checkpoint error",
+            SolveError::InadmissibleHeuristic(_) => "inadmissible heuristic",
+            SolveError::NoSolutionAsked => "no solution asked for",
+        }
+    }
+}
+
+/// Builder gathering everything needed to solve a taquin, so that library
+/// users get the same behaviour as the npuzzle binary
+#[derive(Clone)]
+pub struct Solver<'a> {
+    algorithm: Algorithm,
+    heuristic: &'a dyn Heuristic,
+    goal: Option<Taquin>,
+    automaton: Option<&'a dyn Automaton>,
+    /// Whether idastar loads or builds a cached automaton when none is given
+    default_automaton: bool,
+    table: Option<&'a DistanceTable>,
+    max_nodes: Option<usize>,
+    time_limit: Option<Duration>,
+    threads: usize,
+    /// File and interval of the idastar checkpoints
+    checkpoint: Option<(PathBuf, Duration)>,
+    resume: bool,
+    observer: Option<Observer<'a, u64>>,
+    tie_break: TieBreak,
+    memory: usize,
+    beam: Beam,
+}
+
+impl<'a> Solver<'a> {
+    /// astar with the manhattan heuristic toward the spiral, without limits
+    pub fn new() -> Self {
+        Solver {
+            algorithm: Algorithm::AStar,
+            heuristic: &Manhattan,
+            goal: None,
+            automaton: None,
+            default_automaton: false,
+            table: None,
+            max_nodes: None,
+            time_limit: None,
+            threads: 1,
+            checkpoint: None,
+            resume: false,
+            observer: None,
+            tie_break: TieBreak::default(),
+            memory: sma_star::DEFAULT_MAX_NODES,
+            beam: Beam::default(),
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn heuristic(mut self, heuristic: &'a dyn Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// Goal to reach, the spiral of the taquin dimension by default
+    pub fn goal(mut self, goal: Taquin) -> Self {
+        self.goal = Some(goal);
+        self
+    }
+
+    /// Prunning automaton for the tree searches, see
+    /// `Algorithm::uses_automaton`. By default they only avoid undoing the
+    /// last move.
+    pub fn automaton(mut self, automaton: &'a dyn Automaton) -> Self {
+        self.automaton = Some(automaton);
+        self
+    }
+
+    /// Without automaton, have idastar load one of depth
+    /// `pruning::DEFAULT_DEPTH` from the user cache, building and caching it
+    /// the first time
+    pub fn default_automaton(mut self, default_automaton: bool) -> Self {
+        self.default_automaton = default_automaton;
+        self
+    }
+
+    /// Answer instantly with `table` for the boards it covers
+    pub fn table(mut self, table: &'a DistanceTable) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Give up after expanding `max_nodes` nodes
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Give up after `time_limit`
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Number of threads used by idastar, the other algorithms are sequential
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Save the progress of idastar to `file` every `interval`, it then runs
+    /// on a single thread
+    pub fn checkpoint<P: AsRef<Path>>(mut self, file: P, interval: Duration) -> Self {
+        self.checkpoint = Some((file.as_ref().to_path_buf(), interval));
+        self
+    }
+
+    /// Resume idastar from its checkpoint instead of starting again
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Call `observer` with the progress of the search every `interval`
+    pub fn progress(
+        mut self,
+        observer: &'a (dyn Fn(&Progress<u64>) + Sync),
+        interval: Duration,
+    ) -> Self {
+        self.observer = Some((observer, interval));
+        self
+    }
+
+    /// Order of the states of equal heuristic in greedy search
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Number of nodes sma_star keeps in memory
+    pub fn memory(mut self, max_nodes: usize) -> Self {
+        self.memory = max_nodes;
+        self
+    }
+
+    /// Width and options of the beam search
+    pub fn beam(mut self, beam: Beam) -> Self {
+        self.beam = beam;
+        self
+    }
+
+    /// Goal of `taquin`, checking it can be reached
+    fn goal_of(&self, taquin: &Taquin) -> Result<Taquin, SolveError> {
+        let goal = match self.goal {
+            Some(ref goal) => goal.clone(),
+            None => Taquin::spiral(taquin.dim()),
+        };
+        if goal.dim() != taquin.dim() {
+            return Err(SolveError::DimensionMismatch(taquin.dim(), goal.dim()));
+        }
+        if !taquin.is_solvable(&goal) {
+            return Err(SolveError::Unsolvable);
+        }
+        Ok(goal)
+    }
+
+    pub fn solve(&self, taquin: &Taquin) -> Result<Solution, SolveError> {
+        let goal = self.goal_of(taquin)?;
+        if taquin.is_solved(&goal) {
+            return Ok(Solution {
+                path: vec![taquin.clone()],
+                complexity: Complexity {
+                    in_time: 0,
+                    in_size: 1,
+                },
+            });
+        }
+        if let Some(table) = self.table {
+            if table.is_for(&goal) {
+                let path = table.solve(taquin).ok_or(SolveError::Unsolvable)?;
+                return Ok(Solution {
+                    complexity: Complexity {
+                        in_time: path.len(),
+                        in_size: 1,
+                    },
+                    path,
+                });
+            }
+        }
+
+        let heuristic = match self.algorithm {
+            Algorithm::UniformCost => &Zero,
+            _ => self.heuristic,
+        };
+        let default_automaton;
+        let automaton = match (self.automaton, self.algorithm) {
+            (None, Algorithm::IdaStar) if self.default_automaton => {
+                // if none can be built idastar only avoids undoing the last move
+                default_automaton = pruning::load_or_build(goal.dim(), pruning::DEFAULT_DEPTH).ok();
+                default_automaton.as_ref().map(|a| &**a)
+            }
+            (automaton, _) => automaton,
+        };
+        let mut problem = TaquinProblem::new(goal.clone(), heuristic);
+        if let Some(automaton) = automaton {
+            problem = problem.with_automaton(automaton);
+        }
+        let problem =
+            Bounded::new(problem, self.max_nodes, self.time_limit).observer(self.observer);
+        let sol = match self.algorithm {
+            Algorithm::AStar => astar::search(&problem, taquin),
+            Algorithm::FrontierAStar => frontier_astar::search(&problem, taquin),
+            Algorithm::Rbfs => rbfs::search(&problem, taquin),
+            Algorithm::SmaStar => sma_star::search(&problem, taquin, self.memory),
+            Algorithm::UniformCost => dijkstra::search(&problem, taquin),
+            Algorithm::BreadthFirst => breadth_first::search(&problem, taquin),
+            Algorithm::GreedySearch => {
+                greedy_search::search_with(&problem, taquin, self.tie_break, None)
+            }
+            Algorithm::DepthFirst => depth_first::search(&problem, taquin),
+            Algorithm::Beam => beam_search::search_with(&problem, taquin, self.beam),
+            Algorithm::Reduction => reduction::solve(taquin, &goal).map(|moves| {
+                let mut path = replay(taquin, &moves);
+                path.reverse();
+                let complexity = Complexity {
+                    in_time: moves.len(),
+                    in_size: 1,
+                };
+                (path, complexity)
+            }),
+            Algorithm::IdaStar => {
+                if let Some((ref file, interval)) = self.checkpoint {
+                    idastar::search_resumable(
+                        &problem,
+                        taquin,
+                        goal.zobrist(),
+                        heuristic.name(),
+                        file,
+                        interval,
+                        self.resume,
+                    )
+                    .map_err(|e| SolveError::Checkpoint(e.to_string()))?
+                } else if self.threads > 1 {
+                    idastar::search_parallel(&problem, taquin, self.threads)
+                } else {
+                    idastar::search(&problem, taquin)
+                }
+            }
+        };
+        match sol {
+            Some((mut path, complexity)) => {
+                path.reverse();
+                Ok(Solution { path, complexity })
+            }
+            None => Err(problem.limit_reached().unwrap_or(SolveError::NoSolution)),
+        }
+    }
+
+    /// Moves from `taquin` to the goal. With the reduction algorithm the
+    /// boards in between are never built, they would not fit in memory for
+    /// the biggest boards.
+    pub fn moves(&self, taquin: &Taquin) -> Result<Vec<Dir>, SolveError> {
+        if self.algorithm == Algorithm::Reduction {
+            let goal = self.goal_of(taquin)?;
+            return reduction::solve(taquin, &goal).ok_or(SolveError::NoSolution);
+        }
+        let path = self.solve(taquin)?.path;
+        Ok(path.windows(2).map(|w| w[0].dir_to(&w[1]).unwrap()).collect())
+    }
+
+    /// Every optimal solution with idastar, whatever the algorithm. The
+    /// automaton is not used since it keeps only one of the equivalent
+    /// paths, and the heuristic must be admissible, it is an error otherwise.
+    pub fn all_optimal(
+        &self,
+        taquin: &Taquin,
+        max_solutions: Option<usize>,
+        count_only: bool,
+    ) -> Result<AllSolutions<Dir, u64>, SolveError> {
+        if max_solutions == Some(0) {
+            return Err(SolveError::NoSolutionAsked);
+        }
+        if !self.heuristic.is_admissible() {
+            let name = self.heuristic.name().to_string();
+            return Err(SolveError::InadmissibleHeuristic(name));
+        }
+        let goal = self.goal_of(taquin)?;
+        let problem = Bounded::new(
+            TaquinProblem::new(goal, self.heuristic),
+            self.max_nodes,
+            self.time_limit,
+        )
+        .observer(self.observer);
+        idastar::search_all(&problem, taquin, max_solutions, count_only)
+            .ok_or_else(|| problem.limit_reached().unwrap_or(SolveError::NoSolution))
+    }
+}
+
+/// Every board from `taquin` along `moves`
+fn replay(taquin: &Taquin, moves: &[Dir]) -> Vec<Taquin> {
+    let mut path = vec![taquin.clone()];
+    for &dir in moves {
+        let next = path.last().unwrap().move_piece(dir).unwrap();
+        path.push(next);
+    }
+    path
+}
+
+/// Wraps a problem to interrupt the search once a limit is reached, and to
+/// report its progress
+struct Bounded<'a, P: SearchProblem> {
+    problem: P,
+    max_nodes: Option<usize>,
+    time_limit: Option<Duration>,
+    started: Instant,
+    nodes: AtomicUsize,
+    reached: AtomicBool,
+    observer: Option<Observer<'a, P::Cost>>,
+    /// Last bound and number of open states told by the algorithm
+    reported: Mutex<(Option<P::Cost>, usize)>,
+    last_report: Mutex<Instant>,
+}
+
+impl<'a, P: SearchProblem> Bounded<'a, P> {
+    fn new(problem: P, max_nodes: Option<usize>, time_limit: Option<Duration>) -> Self {
+        Bounded {
+            problem,
+            max_nodes,
+            time_limit,
+            started: Instant::now(),
+            nodes: AtomicUsize::new(0),
+            reached: AtomicBool::new(false),
+            observer: None,
+            reported: Mutex::new((None, 0)),
+            last_report: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn observer(mut self, observer: Option<Observer<'a, P::Cost>>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Call the observer if its interval is over, the threads that find it
+    /// busy go on searching
+    fn report(&self, nodes: usize) {
+        let (observer, interval) = match self.observer {
+            Some(observer) => observer,
+            None => return,
+        };
+        let mut last_report = match self.last_report.try_lock() {
+            Ok(last_report) => last_report,
+            Err(_) => return,
+        };
+        if last_report.elapsed() < interval {
+            return;
+        }
+        *last_report = Instant::now();
+        let (bound, open) = *self.reported.lock().unwrap();
+        observer(&Progress {
+            expanded: nodes,
+            bound,
+            open,
+            elapsed: self.started.elapsed(),
+        });
+    }
+
+    fn limit_reached(&self) -> Option<SolveError> {
+        if !self.reached.load(Ordering::Relaxed) {
+            return None;
+        }
+        let nodes = self.nodes.load(Ordering::Relaxed);
+        match self.max_nodes {
+            Some(max_nodes) if nodes >= max_nodes => Some(SolveError::NodeLimit(nodes)),
+            _ => Some(SolveError::TimeLimit(self.started.elapsed())),
+        }
+    }
+}
+
+impl<'a, P: SearchProblem> SearchProblem for Bounded<'a, P> {
+    type State = P::State;
+    type Action = P::Action;
+    type Cost = P::Cost;
+    type Pruning = P::Pruning;
+
+    fn successors(&self, state: &P::State) -> Vec<(P::Action, P::Cost)> {
+        self.problem.successors(state)
+    }
+    fn apply(&self, state: &P::State, action: P::Action) -> P::State {
+        self.problem.apply(state, action)
+    }
+    fn heuristic(&self, state: &P::State) -> P::Cost {
+        self.problem.heuristic(state)
+    }
+    fn is_goal(&self, state: &P::State) -> bool {
+        self.problem.is_goal(state)
+    }
+    fn initial_pruning(&self) -> P::Pruning {
+        self.problem.initial_pruning()
+    }
+    fn next_pruning(&self, pruning: &P::Pruning, action: P::Action) -> P::Pruning {
+        self.problem.next_pruning(pruning, action)
+    }
+    fn is_pruned(&self, pruning: &P::Pruning) -> bool {
+        self.problem.is_pruned(pruning)
+    }
+    fn interrupted(&self) -> bool {
+        if self.reached.load(Ordering::Relaxed) {
+            return true;
+        }
+        let nodes = self.nodes.fetch_add(1, Ordering::Relaxed) + 1;
+        self.report(nodes);
+        let reached = self.max_nodes.map_or(false, |max_nodes| nodes >= max_nodes)
+            || self
+                .time_limit
+                .map_or(false, |time_limit| self.started.elapsed() >= time_limit)
+            || self.problem.interrupted();
+        if reached {
+            self.reached.store(true, Ordering::Relaxed);
+        }
+        reached
+    }
+    fn progress(&self, bound: Option<P::Cost>, open: usize) {
+        if self.observer.is_none() {
+            return;
+        }
+        if let Ok(mut reported) = self.reported.try_lock() {
+            *reported = (bound, open);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use heuristic::LinearConflict;
+    use std::fs;
+
+    #[test]
+    fn already_solved() {
+        let spiral = Taquin::spiral(3);
+        let solution = Solver::new().solve(&spiral).unwrap();
+        assert_eq!(solution.path, vec![spiral]);
+    }
+    #[test]
+    fn unsolvable() {
+        let taquin = Taquin::new(3, vec![2, 1, 3, 8, 0, 4, 7, 6, 5]);
+        assert_eq!(Solver::new().solve(&taquin).err(), Some(SolveError::Unsolvable));
+    }
+    #[test]
+    fn dimension_mismatch() {
+        let taquin = Taquin::spiral(3);
+        assert_eq!(
+            Solver::new().goal(Taquin::spiral(4)).solve(&taquin).err(),
+            Some(SolveError::DimensionMismatch(3, 4))
+        );
+    }
+    #[test]
+    fn node_limit() {
+        let taquin = Taquin::new(3, vec![8, 1, 3, 0, 7, 2, 6, 5, 4]);
+        match Solver::new().max_nodes(10).solve(&taquin) {
+            Err(SolveError::NodeLimit(_)) => (),
+            _ => panic!("the node limit should have been reached"),
+        }
+    }
+    #[test]
+    fn path_from_start_to_goal() {
+        let taquin = Taquin::new(3, vec![1, 2, 3, 0, 8, 4, 7, 6, 5]);
+        let solution = Solver::new().solve(&taquin).unwrap();
+        assert_eq!(solution.path.first(), Some(&taquin));
+        assert_eq!(solution.path.last(), Some(&Taquin::spiral(3)));
+        assert_eq!(solution.path.len(), 2);
+    }
+    #[test]
+    fn parallel_idastar() {
+        use pruning::PruningAutomaton;
+        let automaton = PruningAutomaton::load("prunning_automaton_3x3_d10.serde")
+            .unwrap()
+            .trie;
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let sequential = Solver::new()
+            .algorithm(Algorithm::IdaStar)
+            .automaton(&automaton)
+            .solve(&taquin)
+            .unwrap();
+        let parallel = Solver::new()
+            .algorithm(Algorithm::IdaStar)
+            .automaton(&automaton)
+            .threads(4)
+            .solve(&taquin)
+            .unwrap();
+        let astar = Solver::new().solve(&taquin).unwrap();
+        assert_eq!(parallel.path.len(), sequential.path.len());
+        assert_eq!(parallel.path.len(), astar.path.len());
+        assert_eq!(parallel.path.first(), Some(&taquin));
+        assert_eq!(parallel.path.last(), Some(&Taquin::spiral(3)));
+    }
+    #[test]
+    fn table() {
+        let spiral = Taquin::spiral(3);
+        let table = DistanceTable::build(&spiral).unwrap();
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let solution = Solver::new().table(&table).solve(&taquin).unwrap();
+        assert_eq!(
+            solution.path.len(),
+            Solver::new().solve(&taquin).unwrap().path.len()
+        );
+    }
+    #[test]
+    fn row_major_goal() {
+        let goal = Taquin::new(3, vec![1, 2, 3, 4, 5, 6, 7, 8, 0]);
+        let taquin = Taquin::new(3, vec![1, 2, 3, 4, 5, 6, 0, 7, 8]);
+        let solution = Solver::new().goal(goal.clone()).solve(&taquin).unwrap();
+        assert_eq!(solution.path.last(), Some(&goal));
+        assert_eq!(solution.path.len(), 3);
+    }
+    #[test]
+    fn uninformed() {
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let optimal = Solver::new().solve(&taquin).unwrap().path.len();
+        for &algorithm in [Algorithm::UniformCost, Algorithm::BreadthFirst].iter() {
+            let solution = Solver::new().algorithm(algorithm).solve(&taquin).unwrap();
+            assert_eq!(solution.path.len(), optimal);
+            assert_eq!(solution.path.first(), Some(&taquin));
+        }
+    }
+    #[test]
+    fn memory_bounded() {
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let optimal = Solver::new().solve(&taquin).unwrap().path.len();
+        let rbfs = Solver::new().algorithm(Algorithm::Rbfs).solve(&taquin);
+        assert_eq!(rbfs.unwrap().path.len(), optimal);
+        let frontier = Solver::new()
+            .algorithm(Algorithm::FrontierAStar)
+            .solve(&taquin);
+        assert_eq!(frontier.unwrap().path.len(), optimal);
+        let sma_star = Solver::new()
+            .algorithm(Algorithm::SmaStar)
+            .memory(200)
+            .solve(&taquin);
+        assert_eq!(sma_star.unwrap().path.len(), optimal);
+    }
+    #[test]
+    fn checkpoint() {
+        let taquin = Taquin::new(3, vec![0, 1, 2, 6, 4, 3, 5, 8, 7]);
+        let optimal = Solver::new().solve(&taquin).unwrap().path.len();
+        let file = ::std::env::temp_dir().join(format!(
+            "npuzzle-idastar-checkpoint-{}",
+            ::std::process::id()
+        ));
+        let solver = Solver::new()
+            .algorithm(Algorithm::IdaStar)
+            .checkpoint(&file, Duration::from_secs(3600));
+        // killed again and again, every run goes on from the last one
+        let mut runs = 1;
+        let mut solution = solver.clone().max_nodes(50).solve(&taquin);
+        while solution.is_err() {
+            assert_eq!(solution.unwrap_err(), SolveError::NodeLimit(50));
+            solution = solver.clone().max_nodes(50).resume(true).solve(&taquin);
+            runs += 1;
+        }
+        assert!(runs > 1);
+        assert_eq!(solution.unwrap().path.len(), optimal);
+        assert!(!file.exists());
+
+        let solution = solver
+            .clone()
+            .checkpoint(&file, Duration::from_secs(0))
+            .solve(&taquin);
+        assert_eq!(solution.unwrap().path.len(), optimal);
+        solver.clone().max_nodes(10).solve(&taquin).unwrap_err();
+        let other = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        match solver.clone().resume(true).solve(&other) {
+            Err(SolveError::Checkpoint(_)) => (),
+            res => panic!("resumed the search of another taquin: {:?}", res.map(|s| s.path)),
+        }
+        let goal = Taquin::spiral(3).move_piece(Dir::Up).unwrap();
+        match solver.clone().goal(goal).resume(true).solve(&taquin) {
+            Err(SolveError::Checkpoint(_)) => (),
+            res => panic!("resumed toward another goal: {:?}", res.map(|s| s.path)),
+        }
+        match solver.heuristic(&LinearConflict).resume(true).solve(&taquin) {
+            Err(SolveError::Checkpoint(_)) => (),
+            res => panic!("resumed with another heuristic: {:?}", res.map(|s| s.path)),
+        }
+        fs::remove_file(file).unwrap();
+    }
+    #[test]
+    fn beam() {
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let optimal = Solver::new().solve(&taquin).unwrap().path.len();
+        let solver = Solver::new().algorithm(Algorithm::Beam);
+        let solution = solver.clone().beam(Beam::new(20)).solve(&taquin).unwrap();
+        assert!(solution.path.len() >= optimal);
+        assert_eq!(solution.path.last(), Some(&Taquin::spiral(3)));
+        assert_eq!(
+            solver.beam(Beam::new(1)).solve(&taquin).unwrap_err(),
+            SolveError::NoSolution
+        );
+    }
+    #[test]
+    fn progress() {
+        let taquin = Taquin::new(3, vec![8, 1, 3, 0, 7, 2, 6, 5, 4]);
+        let reports = Mutex::new(Vec::new());
+        let observer = |progress: &Progress<u64>| reports.lock().unwrap().push(progress.clone());
+        let solver = Solver::new().progress(&observer, Duration::from_secs(0));
+        let cost = solver.clone().solve(&taquin).unwrap().path.len() as u64 - 1;
+        {
+            // astar expands by increasing f-cost
+            let reports = reports.lock().unwrap();
+            assert!(reports.iter().any(|progress| progress.open > 0));
+            assert!(reports
+                .windows(2)
+                .all(|w| w[0].expanded < w[1].expanded && w[0].bound <= w[1].bound));
+            assert_eq!(reports.last().unwrap().bound, Some(cost));
+        }
+        reports.lock().unwrap().clear();
+        solver.algorithm(Algorithm::IdaStar).solve(&taquin).unwrap();
+        let reports = reports.into_inner().unwrap();
+        assert!(reports.iter().all(|progress| progress.open == 0));
+        assert!(reports.windows(2).all(|w| w[0].bound <= w[1].bound));
+        assert_eq!(reports.last().unwrap().bound, Some(cost));
+    }
+    #[test]
+    fn reduction() {
+        let taquin = Taquin::new(3, vec![5, 1, 0, 8, 4, 6, 3, 7, 2]);
+        let solver = Solver::new().algorithm(Algorithm::Reduction);
+        let solution = solver.solve(&taquin).unwrap();
+        assert_eq!(solution.path.first(), Some(&taquin));
+        assert_eq!(solution.path.last(), Some(&Taquin::spiral(3)));
+        assert_eq!(solver.moves(&taquin).unwrap().len(), solution.path.len() - 1);
+        assert_eq!(
+            Solver::new().moves(&taquin).unwrap().len(),
+            Solver::new().solve(&taquin).unwrap().path.len() - 1
+        );
+    }
+    #[test]
+    fn all_optimal() {
+        let spiral = Taquin::spiral(3);
+        let table = DistanceTable::build(&spiral).unwrap();
+        // number of shortest paths to the goal, neighbour by neighbour
+        fn nb_paths(table: &DistanceTable, taquin: &Taquin) -> usize {
+            let d = table.distance(taquin).unwrap();
+            if d == 0 {
+                return 1;
+            }
+            taquin
+                .neighbours()
+                .into_iter()
+                .map(|dir| taquin.move_piece(dir).unwrap())
+                .filter(|n| table.distance(n) == Some(d - 1))
+                .map(|n| nb_paths(table, &n))
+                .sum()
+        }
+        let taquin = Taquin::new(3, vec![0, 1, 2, 6, 4, 3, 5, 8, 7]);
+        let all = Solver::new().all_optimal(&taquin, None, false).unwrap();
+        assert_eq!(all.cost, table.distance(&taquin).unwrap());
+        assert_eq!(all.nb_solutions, nb_paths(&table, &taquin));
+        assert!(all.nb_solutions > 1 && !all.truncated);
+        for moves in all.solutions.iter() {
+            let end = moves
+                .iter()
+                .fold(taquin.clone(), |t, &d| t.move_piece(d).unwrap());
+            assert_eq!(moves.len() as u64, all.cost);
+            assert!(end.is_solved(&spiral));
+        }
+
+        let counted = Solver::new().all_optimal(&taquin, None, true).unwrap();
+        assert_eq!(counted.nb_solutions, all.nb_solutions);
+        assert!(counted.solutions.is_empty());
+        let capped = Solver::new().all_optimal(&taquin, Some(2), false).unwrap();
+        assert_eq!(capped.solutions.len(), 2);
+        assert!(capped.truncated);
+        assert_eq!(
+            Solver::new()
+                .heuristic(&LinearConflict)
+                .all_optimal(&taquin, None, false)
+                .unwrap_err(),
+            SolveError::InadmissibleHeuristic(String::from("linear_conflict"))
+        );
+        assert_eq!(
+            Solver::new().all_optimal(&taquin, Some(0), false).unwrap_err(),
+            SolveError::NoSolutionAsked
+        );
+    }
+}
@@ -1,10 +1,12 @@
 use rand::{thread_rng, Rng};
+use rank;
 use std::error::Error;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::num::ParseIntError;
 use std::str::FromStr;
 use visualizable::*;
+use zobrist;
 
 #[derive(Copy, Hash, Clone, Debug, PartialEq, Eq)]
 pub enum Dir {
@@ -50,6 +52,8 @@ pub struct Taquin {
     n: usize,
     pieces: Vec<u64>,
     cur_pos: usize,
+    /// Zobrist hash of the pieces, updated by `move_piece`
+    zobrist: u64,
 }
 
 impl Hash for Taquin {
@@ -57,7 +61,7 @@ impl Hash for Taquin {
     where
         H: Hasher,
     {
-        self.pieces.hash(state)
+        state.write_u64(self.zobrist)
     }
 }
 
@@ -73,7 +77,13 @@ impl Taquin {
         debug_assert!((0..n * n).all(|i| pieces.iter().any(|&k| k == i as u64)));
         let cur_pos = pieces.iter().position(|&x| x == 0).unwrap();
         assert_eq!(pieces.len(), n * n);
-        Taquin { n, pieces, cur_pos }
+        let zobrist = zobrist::hash(&pieces);
+        Taquin {
+            n,
+            pieces,
+            cur_pos,
+            zobrist,
+        }
     }
 
     pub fn sorted_neighbours<'a, FH: Fn(&Self) -> u64>(&self, heuristic: &FH) -> Vec<Dir> {
@@ -138,13 +148,23 @@ impl Taquin {
         let index_to_go = Taquin::get_index(&dir, self.cur_pos, self.n)?;
         let mut new_pieces = self.pieces.clone();
         new_pieces.swap(self.cur_pos, index_to_go);
+        let moved = new_pieces[self.cur_pos];
+        let z = zobrist::move_tile(self.zobrist, moved, index_to_go, self.cur_pos);
         Some(Taquin {
             n: self.n,
             pieces: new_pieces,
             cur_pos: index_to_go,
+            zobrist: zobrist::move_tile(z, 0, self.cur_pos, index_to_go),
         })
     }
 
+    /// Move leading to `other`, None if it is not a neighbour
+    pub fn dir_to(&self, other: &Taquin) -> Option<Dir> {
+        self.neighbours()
+            .into_iter()
+            .find(|&dir| self.move_piece(dir).as_ref() == Some(other))
+    }
+
     pub fn spiral(n: usize) -> Self {
         let mut pieces: Vec<u64> = vec![0; n * n];
         let mut i = 0;
@@ -170,6 +190,40 @@ impl Taquin {
         Self::new(n, pieces)
     }
 
+    /// Lehmer rank of the board among the (n * n)! permutations, None above
+    /// 4x4 where it doesn't fit in a u64
+    pub fn rank(&self) -> Option<u64> {
+        if self.pieces.len() > rank::MAX_RANK_LEN {
+            return None;
+        }
+        Some(rank::lehmer_rank(&self.pieces))
+    }
+
+    /// Board of rank `r`, None above 4x4 or if there are less than `r + 1`
+    /// boards
+    pub fn from_rank(n: usize, r: u64) -> Option<Self> {
+        if n * n > rank::MAX_RANK_LEN || r >= rank::nb_permutations(n * n) {
+            return None;
+        }
+        Some(Self::new(n, rank::lehmer_unrank(n * n, r)))
+    }
+
+    /// Rank of the positions of `tiles`, the other tiles being
+    /// indistinguishable
+    pub fn partial_rank(&self, tiles: &[u64]) -> u64 {
+        let mut positions = vec![0; self.pieces.len()];
+        for (i, &p) in self.pieces.iter().enumerate() {
+            positions[p as usize] = i;
+        }
+        let tile_positions: Vec<usize> = tiles.iter().map(|&t| positions[t as usize]).collect();
+        rank::partial_rank(&tile_positions, self.pieces.len())
+    }
+
+    /// Zobrist hash of the board
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
     pub fn iter(&self) -> ::std::slice::Iter<u64> {
         self.pieces.iter()
     }
@@ -325,7 +379,12 @@ impl Taquin {
     /// Returns weither or not the state of the taquin is solvable
     pub fn is_solvable(&self, static_spiral: &Taquin) -> bool {
         let nb_trans = self.nb_transposition(static_spiral);
-        let nb_move = self.nb_move_zero();
+        // nb move to put the zero at its place in the goal
+        let nb_move = Self::manhattan_distance(
+            self.cur_pos as i64,
+            static_spiral.cur_pos as i64,
+            self.n as i64,
+        );
 
         // the taquin is solvable if nb_trans and nb_move have the same parity
         (nb_trans + nb_move) % 2 == 0
@@ -534,6 +593,7 @@ impl From<ParseIntError> for ParseTaquinError {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::random;
     #[test]
     fn empty() {
         let s = "# This puzzle is solvable
@@ -737,6 +797,29 @@ mod test {
         assert!(!taquin.is_solved(&s));
     }
     #[test]
+    fn solvable() {
+        for n in 2..6 {
+            let row_major = Taquin::new(n, (1..n * n).map(|t| t as u64).chain(Some(0)).collect());
+            for goal in [row_major, Taquin::spiral(n)].iter() {
+                // random walks from the goal, then the same with two tiles swapped
+                let mut taquin = goal.clone();
+                for _ in 0..100 {
+                    let neighbours = taquin.neighbours();
+                    let dir = neighbours[random::<usize>() % neighbours.len()];
+                    taquin = taquin.move_piece(dir).unwrap();
+                    assert!(taquin.is_solvable(goal));
+                    let mut pieces: Vec<u64> = taquin.iter().cloned().collect();
+                    let (a, b) = match taquin.cur_pos {
+                        0 | 1 => (2, 3),
+                        _ => (0, 1),
+                    };
+                    pieces.swap(a, b);
+                    assert!(!Taquin::new(n, pieces).is_solvable(goal));
+                }
+            }
+        }
+    }
+    #[test]
     fn oposite() {
         assert_eq!(Dir::Right.oposite(), Dir::Left);
         assert_eq!(Dir::Left.oposite(), Dir::Right);
@@ -744,6 +827,44 @@ mod test {
         assert_eq!(Dir::Down.oposite(), Dir::Up);
     }
     #[test]
+    fn zobrist() {
+        let mut taquin = Taquin::new_random(5);
+        for _ in 0..1000 {
+            let dirs = taquin.neighbours();
+            taquin = taquin.move_piece(dirs[random::<usize>() % dirs.len()]).unwrap();
+            assert_eq!(taquin.zobrist(), zobrist::hash(&taquin.pieces));
+        }
+        assert_ne!(Taquin::spiral(3).zobrist(), Taquin::from_rank(3, 0).unwrap().zobrist());
+    }
+    #[test]
+    fn rank() {
+        for r in 0..rank::nb_permutations(9) {
+            assert_eq!(Taquin::from_rank(3, r).unwrap().rank(), Some(r));
+        }
+        let spiral = Taquin::spiral(4);
+        assert_eq!(Taquin::from_rank(4, spiral.rank().unwrap()), Some(spiral));
+        assert_eq!(Taquin::from_rank(3, rank::nb_permutations(9)), None);
+        assert_eq!(Taquin::spiral(5).rank(), None);
+        assert_eq!(Taquin::from_rank(5, 0), None);
+    }
+    #[test]
+    fn partial_rank() {
+        // with every tile it ranks the inverse permutation
+        let tiles = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+        for r in 0..1000 {
+            let taquin = Taquin::from_rank(3, r * 360).unwrap();
+            let mut inverse = vec![0; 9];
+            for (i, &p) in taquin.iter().enumerate() {
+                inverse[p as usize] = i as u64;
+            }
+            assert_eq!(taquin.partial_rank(&tiles), Taquin::new(3, inverse).rank().unwrap());
+        }
+        let a = Taquin::new(3, vec![1, 2, 3, 8, 0, 4, 7, 6, 5]);
+        let b = Taquin::new(3, vec![1, 2, 3, 8, 0, 4, 6, 7, 5]);
+        assert_eq!(a.partial_rank(&[0, 1, 2]), b.partial_rank(&[0, 1, 2]));
+        assert_ne!(a.partial_rank(&[0, 6, 7]), b.partial_rank(&[0, 6, 7]));
+    }
+    #[test]
     #[should_panic]
     fn new_taquin() {
         let taquin = Taquin::new(3, vec![5, 10, 0, 8, 4, 6, 3, 7, 2]);
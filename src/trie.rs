@@ -1,3 +1,6 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
 use std::ops::Index;
 use std::ops::IndexMut;
 use taquin::Dir;
@@ -29,16 +32,61 @@ use self::TrieType::*;
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Trie(pub Vec<TrieNode>);
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrieError {
+    /// `word` ends on the node `state`, inside a longer redundant word
+    SubwordOfRedundant { word: Vec<Dir>, state: usize },
+    /// Every transition from `state` is redundant
+    AllRedundant { state: usize },
+    /// Paths longer than `max` moves can't be packed by the layered builder
+    DepthTooBig { depth: usize, max: usize },
+    /// A thread of the layered builder panicked
+    WorkerPanicked,
+}
+
+impl fmt::Display for TrieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrieError::SubwordOfRedundant { ref word, state } => write!(
+                f,
+                "{:?} is a subword of a redundant word (state {})",
+                word, state
+            ),
+            TrieError::AllRedundant { state } => {
+                write!(f, "every transition from state {} is redundant", state)
+            }
+            TrieError::DepthTooBig { depth, max } => write!(
+                f,
+                "can't build a prunning automaton of depth {}, the maximum is {}",
+                depth, max
+            ),
+            TrieError::WorkerPanicked => write!(f, "a thread building the automaton panicked"),
+        }
+    }
+}
+
+impl Error for TrieError {
+    fn description(&self) -> &str {
+        match *self {
+            TrieError::SubwordOfRedundant { .. } => "subword of a redundant",
+            TrieError::AllRedundant { .. } => "all redundant",
+            TrieError::DepthTooBig { .. } => "depth too big",
+            TrieError::WorkerPanicked => "worker thread panicked",
+        }
+    }
+}
+
 impl Trie {
     pub fn new() -> Self {
         Trie(vec![TrieNode([Failure(0); 4])])
     }
-    pub fn check_integrity(&self) {
+    pub fn check_integrity(&self) -> Result<(), TrieError> {
         for x in 0..self.0.len() {
             if self.all_redundant(x) {
-                panic!("all redundant");
+                return Err(TrieError::AllRedundant { state: x });
             }
         }
+        Ok(())
     }
     pub fn all_redundant(&self, state: usize) -> bool {
         self.0[state].0.iter().all(|x| *x == Redundant)
@@ -75,34 +123,46 @@ impl Trie {
         let mut path = Vec::new();
         self.update_failure_aux(0, &mut path);
     }
-    fn add_word_aux(&mut self, state: usize, word: &Vec<Dir>, i: usize, debug: bool) {
+    fn add_word_aux(
+        &mut self,
+        state: usize,
+        word: &Vec<Dir>,
+        i: usize,
+        debug: bool,
+    ) -> Result<(), TrieError> {
         if let Some(letter) = word.get(i) {
             match self.0[state][*letter] {
                 Match(next_state) => {
+                    // the trie is left untouched
                     if i == word.len() - 1 {
-                        self.0[state][*letter] = Redundant;
-                        panic!("subword of a redundant");
+                        return Err(TrieError::SubwordOfRedundant {
+                            word: word.clone(),
+                            state,
+                        });
                     } else {
-                        self.add_word_aux(next_state, word, i + 1, debug)
+                        return self.add_word_aux(next_state, word, i + 1, debug);
                     }
                 }
                 Failure(_) => self.new_down(state, *letter, word, i, debug),
                 Redundant => {
                     //panic!("already redundant");
-                    return;
+                    return Ok(());
                 }
             }
         }
+        Ok(())
     }
-    pub fn add_word(&mut self, word: &Vec<Dir>, debug: bool) -> bool {
+    /// Add a redundant word, false if it already was. The trie is left
+    /// unchanged when an error is returned.
+    pub fn add_word(&mut self, word: &Vec<Dir>, debug: bool) -> Result<bool, TrieError> {
         if let Redundant = self.greatest_match(word) {
             if debug {
                 println!("already redundant");
             }
-            return false;
+            return Ok(false);
         }
-        self.add_word_aux(0, word, 0, debug);
-        return true;
+        self.add_word_aux(0, word, 0, debug)?;
+        Ok(true)
     }
     fn new_down(&mut self, state: usize, curr_letter: Dir, word: &Vec<Dir>, i: usize, debug: bool) {
         if i == word.len() - 1 {
@@ -139,6 +199,17 @@ impl Trie {
     }
 }
 
+/// Follows the transitions of a pruning automaton, whatever its layout
+pub trait Automaton: Sync {
+    fn change_true_state(&self, old_state: &TrieType, dir: Dir) -> TrieType;
+}
+
+impl Automaton for Trie {
+    fn change_true_state(&self, old_state: &TrieType, dir: Dir) -> TrieType {
+        Trie::change_true_state(self, old_state, dir)
+    }
+}
+
 impl<'a> Trie {
     pub fn match_word<I: Iterator<Item = &'a Dir>>(&self, word: I) -> TrieType {
         let mut state = 0;
@@ -171,6 +242,197 @@ impl<'a> Trie {
     }
 }
 
+impl Trie {
+    /// Merge the equivalent states, Hopcroft's algorithm with `Redundant` as a
+    /// sink. The result matches the same words but is no longer a trie, no
+    /// word can be added to it.
+    pub fn minimise(&self) -> Trie {
+        let sink = self.0.len();
+        let target = |state: usize, letter: usize| -> usize {
+            if state == sink {
+                return sink;
+            }
+            match self.0[state].0[letter] {
+                Redundant => sink,
+                Failure(next) | Match(next) => next,
+            }
+        };
+        // predecessors of each state on each letter
+        let mut inverse = vec![vec![Vec::new(); sink + 1]; 4];
+        for state in 0..sink + 1 {
+            for letter in 0..4 {
+                inverse[letter][target(state, letter)].push(state);
+            }
+        }
+
+        let mut blocks = vec![(0..sink).collect::<Vec<_>>(), vec![sink]];
+        let mut block_of = vec![0; sink + 1];
+        block_of[sink] = 1;
+        let mut waiting: Vec<[bool; 4]> = vec![[false; 4], [true; 4]];
+        let mut worklist: Vec<(usize, usize)> = (0..4).map(|letter| (1, letter)).collect();
+        let mut marked = vec![false; sink + 1];
+
+        while let Some((splitter, letter)) = worklist.pop() {
+            waiting[splitter][letter] = false;
+            // states going into the splitter, grouped by block
+            let mut touched: HashMap<usize, Vec<usize>> = HashMap::new();
+            for &state in blocks[splitter].iter() {
+                for &pred in inverse[letter][state].iter() {
+                    touched.entry(block_of[pred]).or_insert_with(Vec::new).push(pred);
+                }
+            }
+            for (block, members) in touched {
+                if members.len() == blocks[block].len() {
+                    continue;
+                }
+                for &state in members.iter() {
+                    marked[state] = true;
+                }
+                let rest: Vec<usize> = blocks[block].iter().cloned().filter(|&s| !marked[s]).collect();
+                let new_block = blocks.len();
+                for &state in members.iter() {
+                    marked[state] = false;
+                    block_of[state] = new_block;
+                }
+                let new_is_smaller = members.len() <= rest.len();
+                blocks[block] = rest;
+                blocks.push(members);
+                waiting.push([false; 4]);
+                for l in 0..4 {
+                    if waiting[block][l] {
+                        waiting[new_block][l] = true;
+                        worklist.push((new_block, l));
+                    } else {
+                        let smaller = if new_is_smaller { new_block } else { block };
+                        waiting[smaller][l] = true;
+                        worklist.push((smaller, l));
+                    }
+                }
+            }
+        }
+
+        // number the blocks in breadth first order from the root's one
+        let mut index = vec![usize::MAX; blocks.len()];
+        let mut order = vec![block_of[0]];
+        index[block_of[0]] = 0;
+        let mut i = 0;
+        while i < order.len() {
+            let representative = blocks[order[i]][0];
+            for letter in 0..4 {
+                let next = block_of[target(representative, letter)];
+                if next != block_of[sink] && index[next] == usize::MAX {
+                    index[next] = order.len();
+                    order.push(next);
+                }
+            }
+            i += 1;
+        }
+        let nodes = order
+            .iter()
+            .map(|&block| {
+                let representative = blocks[block][0];
+                let mut node = self.0[representative];
+                for t in node.0.iter_mut() {
+                    *t = match *t {
+                        Redundant => Redundant,
+                        Failure(next) => Failure(index[block_of[next]]),
+                        Match(next) => Match(index[block_of[next]]),
+                    };
+                }
+                node
+            })
+            .collect();
+        Trie(nodes)
+    }
+}
+
+/// Introspection, see `prune inspect`
+impl Trie {
+    /// Length of the shortest word reaching every node, its depth in the tree
+    /// of `Match` transitions before minimisation
+    pub fn depths(&self) -> Vec<usize> {
+        let mut depths = vec![usize::MAX; self.0.len()];
+        let mut queue = VecDeque::new();
+        depths[0] = 0;
+        queue.push_back(0);
+        while let Some(state) = queue.pop_front() {
+            for t in self.0[state].0.iter() {
+                match *t {
+                    Failure(next) | Match(next) if depths[next] == usize::MAX => {
+                        depths[next] = depths[state] + 1;
+                        queue.push_back(next);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        depths
+    }
+    pub fn nb_redundant(&self) -> usize {
+        self.0
+            .iter()
+            .map(|node| node.0.iter().filter(|t| **t == Redundant).count())
+            .sum()
+    }
+    /// Fraction of the move sequences of each length up to `max_len` that are
+    /// pruned, counting the impossible moves too
+    pub fn pruned_fractions(&self, max_len: usize) -> Vec<f64> {
+        let mut mass = vec![0.; self.0.len()];
+        mass[0] = 1.;
+        let mut pruned = 0.;
+        let mut fractions = Vec::with_capacity(max_len);
+        for _ in 0..max_len {
+            let mut next_mass = vec![0.; self.0.len()];
+            for (state, m) in mass.iter().enumerate() {
+                for t in self.0[state].0.iter() {
+                    match *t {
+                        Redundant => pruned += m / 4.,
+                        Failure(next) | Match(next) => next_mass[next] += m / 4.,
+                    }
+                }
+            }
+            mass = next_mass;
+            fractions.push(pruned);
+        }
+        fractions
+    }
+    /// Graphviz graph of the nodes up to `max_depth`. Failure transitions are
+    /// dashed, except those back to the root which are left out, and every
+    /// redundant transition goes to the same sink.
+    pub fn to_dot(&self, max_depth: usize) -> String {
+        let depths = self.depths();
+        let mut dot = String::from("digraph trie {\n    redundant [shape=box, color=red];\n");
+        for (state, node) in self.0.iter().enumerate() {
+            if depths[state] > max_depth {
+                continue;
+            }
+            dot += &format!("    {} [label=\"{}\"];\n", state, state);
+            for (i, t) in node.0.iter().enumerate() {
+                let dir = Dir::from(i);
+                match *t {
+                    Redundant => {
+                        dot += &format!(
+                            "    {} -> redundant [label=\"{:?}\", color=red];\n",
+                            state, dir
+                        )
+                    }
+                    Match(next) if depths[next] <= max_depth => {
+                        dot += &format!("    {} -> {} [label=\"{:?}\"];\n", state, next, dir)
+                    }
+                    Failure(next) if next != 0 && depths[next] <= max_depth => {
+                        dot += &format!(
+                            "    {} -> {} [label=\"{:?}\", style=dashed];\n",
+                            state, next, dir
+                        )
+                    }
+                    _ => (),
+                }
+            }
+        }
+        dot + "}\n"
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -182,7 +444,7 @@ mod test {
         println!("trie: {:?}", trie);
 
         let path = vec![Dir::Right, Dir::Right];
-        trie.add_word(&path, false);
+        trie.add_word(&path, false).unwrap();
         println!("trie: {:?}", trie);
         assert_eq!(trie.match_word(path.iter()), Redundant);
         assert_ne!(
@@ -191,8 +453,41 @@ mod test {
         );
     }
     #[test]
+    fn inspect() {
+        let mut trie = Trie::new();
+        trie.add_word(&vec![Dir::Right, Dir::Left], false).unwrap();
+        trie.update_failure();
+        assert_eq!(trie.depths(), vec![0, 1]);
+        assert_eq!(trie.nb_redundant(), 1);
+        let fractions = trie.pruned_fractions(3);
+        assert_eq!(fractions[..2], [0., 1. / 16.]);
+        // RLx and xRL
+        assert_eq!(fractions[2], 8. / 64.);
+        assert!(trie.to_dot(1).contains("1 -> redundant"));
+    }
+    #[test]
+    fn minimise() {
+        let choices = [Dir::Up, Dir::Right, Dir::Left, Dir::Down];
+        let mut rng = thread_rng();
+        for &(dim, depth) in [(3, 10), (4, 8)].iter() {
+            let (trie, _, _) = construct_pruning_trie(dim, depth).unwrap();
+            let minimised = trie.minimise();
+            assert!(minimised.0.len() < trie.0.len());
+            assert_eq!(minimised.minimise(), minimised);
+            for _ in 0..10_000 {
+                let word: Vec<Dir> = (0..random::<usize>() % 30)
+                    .map(|_| *rng.choose(&choices).unwrap())
+                    .collect();
+                assert_eq!(
+                    trie.match_word(word.iter()) == Redundant,
+                    minimised.match_word(word.iter()) == Redundant
+                );
+            }
+        }
+    }
+    #[test]
     fn test_all_redundant_path() {
-        let (trie, all_redundant_pahts, primitive_paths) = construct_pruning_trie(4, 13);
+        let (trie, all_redundant_pahts, primitive_paths) = construct_pruning_trie(4, 13).unwrap();
         //trie.check_integrity();
         //    println!("trie: {:#?}", trie);
         println!("len: {:#?}", all_redundant_pahts.len());
@@ -263,10 +558,10 @@ mod test {
         let v4 = vec![
             Right, Up, Left, Down, Left, Up, Left, Down, Left, Up, Right, Right, Up, Left,
         ];
-        trie.add_word(&v1, false);
-        trie.add_word(&v2, false);
-        trie.add_word(&v3, false);
-        trie.add_word(&v4, false);
+        trie.add_word(&v1, false).unwrap();
+        trie.add_word(&v2, false).unwrap();
+        trie.add_word(&v3, false).unwrap();
+        trie.add_word(&v4, false).unwrap();
         trie.update_failure();
         assert_eq!(trie.match_word(v1.iter()), Redundant);
         assert_eq!(trie.match_word(v2.iter()), Redundant);
@@ -279,9 +574,9 @@ mod test {
         let v1 = vec![Right, Up, Left, Down, Right];
 
         let v2 = vec![Up, Left, Down];
-        trie.add_word(&v1, false);
+        trie.add_word(&v1, false).unwrap();
         println!("trie: {:#?}", trie);
-        trie.add_word(&v2, false);
+        trie.add_word(&v2, false).unwrap();
         println!("trie: {:#?}", trie);
         trie.update_failure();
         println!("trie: {:#?}", trie);
@@ -292,10 +587,17 @@ mod test {
         let mut trie = Trie::new();
         let big = vec![Dir::Up, Dir::Right, Dir::Left, Dir::Down, Dir::Up];
         let sub_big = vec![Dir::Up, Dir::Right, Dir::Left, Dir::Down];
-        trie.add_word(&big, false);
-        trie.add_word(&sub_big, false);
+        trie.add_word(&big, false).unwrap();
+        let before = trie.clone();
+        assert_eq!(
+            trie.add_word(&sub_big, false),
+            Err(TrieError::SubwordOfRedundant {
+                word: sub_big.clone(),
+                state: 3
+            })
+        );
+        assert_eq!(trie, before);
         assert_eq!(trie.match_word(big.iter()), Redundant);
-        assert_eq!(trie.match_word(sub_big.iter()), Redundant);
     }
     use std::collections::HashSet;
     const MAX_SIZE_TEST: usize = 10;
@@ -314,7 +616,7 @@ mod test {
                     primitive.insert(v);
                 }
             } else if !primitive.contains(&v) && rng.gen() {
-                trie.add_word(&v, false);
+                trie.add_word(&v, false).unwrap();
                 redundant.insert(v);
             }
         }
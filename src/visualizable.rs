@@ -8,6 +8,8 @@ pub use sdl2::surface::Surface;
 pub use sdl2::video::WindowSurfaceRef;
 use std::time::Duration;
 
+use heuristic::Heuristic;
+use lrta_star::{Agent, LearnedTable};
 use taquin::{Dir, Taquin};
 
 pub const WINDOW_SIZE: u32 = 1080;
@@ -32,6 +34,7 @@ pub fn visualize_path<P: AsRef<Path> + ::std::fmt::Display>(
     path: Vec<Taquin>,
     image_path: P,
     goal_taquin: &Taquin,
+    heuristic: &dyn Heuristic,
 	truncation_42: bool,
 ) -> Result<(), ()> {
     if path.len() == 0 {
@@ -104,6 +107,9 @@ pub fn visualize_path<P: AsRef<Path> + ::std::fmt::Display>(
     let duration_granularity = Duration::new(0, 1_000_000_000u32 / 20);
     let mut playing = false;
     let mut playing_taquin = path.iter().nth(0).unwrap().clone();
+    // plays a move for the player on H, learning across sessions
+    let mut agent = Agent::new(playing_taquin.clone(), spiral.clone(), heuristic)
+        .table(LearnedTable::load_or_new(&spiral));
     'running: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -144,6 +150,17 @@ pub fn visualize_path<P: AsRef<Path> + ::std::fmt::Display>(
                         playing_taquin = new_state;
                     }
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::H),
+                    ..
+                } => {
+                    if playing {
+                        if let Some(dir) = agent.choose(&playing_taquin) {
+                            println!("Hint: {:?}", dir);
+                            playing_taquin = playing_taquin.move_piece(dir).unwrap();
+                        }
+                    }
+                }
                 Event::KeyDown {
                     keycode: Some(Keycode::R),
                     ..
@@ -255,5 +272,10 @@ pub fn visualize_path<P: AsRef<Path> + ::std::fmt::Display>(
         }
         ::std::thread::sleep(frame_duration);
     }
+    if !agent.learned().is_empty() {
+        if let Err(e) = agent.learned().save_in_cache() {
+            eprintln!("Failed to save the learned hints: {}", e);
+        }
+    }
     Ok(())
 }
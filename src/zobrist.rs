@@ -0,0 +1,72 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// Zobrist key of `tile` at `position`.
+///
+/// Keys are derived with splitmix64 instead of being drawn from a random table
+/// so that every board size shares them without any initialisation.
+pub fn key(tile: u64, position: usize) -> u64 {
+    let mut z = (tile << 32 | position as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Zobrist hash of a whole board
+pub fn hash(pieces: &[u64]) -> u64 {
+    pieces
+        .iter()
+        .enumerate()
+        .fold(0, |h, (position, &tile)| h ^ key(tile, position))
+}
+
+/// Zobrist hash after moving `tile` from `from` to `to`
+pub fn move_tile(hash: u64, tile: u64, from: usize, to: usize) -> u64 {
+    hash ^ key(tile, from) ^ key(tile, to)
+}
+
+/// Hasher keeping the zobrist hash written by `Taquin::hash` with a single
+/// bijective multiplication. Other keys are hashed with FNV-1a, their `u64`
+/// fields being mixed in as whole words.
+pub struct ZobristHasher(u64);
+
+impl Default for ZobristHasher {
+    fn default() -> Self {
+        ZobristHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for ZobristHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0 ^ i).wrapping_mul(0x100_0000_01b3);
+    }
+}
+
+/// To build the `HashMap` and `HashSet` of boards
+pub type BuildZobristHasher = BuildHasherDefault<ZobristHasher>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::hash::Hash;
+
+    fn hash_of<T: Hash>(t: &T) -> u64 {
+        let mut hasher = ZobristHasher::default();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn mixing() {
+        assert_ne!(hash_of(&(1u64, 2u64)), hash_of(&(2u64, 1u64)));
+        assert_ne!(hash_of(&(3u64, 3u64)), hash_of(&(4u64, 4u64)));
+        assert_ne!(hash_of(&7u64), hash_of(&8u64));
+    }
+}